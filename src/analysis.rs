@@ -0,0 +1,227 @@
+use crate::{bot::Bot, game::*, notation, rules::RuleSet};
+
+// Search depth used by the engine when analysing a finished game
+pub const ANALYSIS_DEPTH: u64 = 3;
+
+// Evaluation drops from which a decision is flagged
+const MISTAKE_DROP: f64 = 10.0;
+const BLUNDER_DROP: f64 = 25.0;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Judgement {
+    Good,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    fn from_drop(drop: f64) -> Self {
+        if drop >= BLUNDER_DROP {
+            Judgement::Blunder
+        } else if drop >= MISTAKE_DROP {
+            Judgement::Mistake
+        } else {
+            Judgement::Good
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Judgement::Good => "",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+}
+
+// A turn with its evaluation and the evaluation of the best alternative, both for the colour making it
+#[derive(Debug, Clone)]
+pub struct TurnAnalysis {
+    pub turn: Turn,
+    pub score: f64,
+    pub best_turn: Turn,
+    pub best_score: f64,
+    pub judgement: Judgement,
+}
+
+// The decision of the other players whether to call second best, the drop is for the caller,
+// or for the players that could have called when nobody did
+#[derive(Debug, Clone)]
+pub struct SecondBestAnalysis {
+    pub caller: Option<Colour>,
+    pub could_call: Vec<Colour>,
+    pub should_call: bool,
+    pub drop: f64,
+    pub judgement: Judgement,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoundAnalysis {
+    pub colour: Colour,
+    pub proposed: TurnAnalysis,
+    pub second_best: SecondBestAnalysis,
+    pub second_turn: Option<TurnAnalysis>,
+}
+
+// Replay a game and evaluate every proposed turn and second best decision
+pub fn analyse_game(game: &Game, depth: u64) -> Vec<RoundAnalysis> {
+    let rules = &game.rules;
+    let mut board = Board::with_config(rules.board);
+    let mut analysis = Vec::new();
+    for (i, (round, caller)) in game.rounds.iter().zip(game.callers()).enumerate() {
+        let colour = round.proposed.colour();
+        let bot = Bot::new(colour, depth).with_rules(rules.clone());
+        let scored_turns = bot.score_turns(&board, colour);
+
+        let proposed = analyse_turn(&round.proposed, &scored_turns, None);
+
+        // What the proposing colour would get out of its best turn after second best
+        let retry_score = scored_turns
+            .iter()
+            .filter(|(turn, _)| *turn != round.proposed || rules.replay_vetoed)
+            .map(|(_, score)| *score)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let could_call = could_call(rules, &game.callers()[..i], colour, *caller, scored_turns.len());
+        let called = caller.is_some();
+        let should_call = !could_call.is_empty() && retry_score < proposed.score;
+        let drop = if called == should_call || !retry_score.is_finite() {
+            0.0
+        } else {
+            (proposed.score - retry_score).abs()
+        };
+        let second_best = SecondBestAnalysis {
+            caller: *caller,
+            could_call,
+            should_call,
+            drop,
+            judgement: Judgement::from_drop(drop),
+        };
+
+        let second_turn = round
+            .second_turn
+            .as_ref()
+            .map(|turn| analyse_turn(turn, &scored_turns, Some(&round.proposed)));
+
+        board.do_turn(round.played());
+        analysis.push(RoundAnalysis {
+            colour,
+            proposed,
+            second_best,
+            second_turn,
+        });
+    }
+    analysis
+}
+
+// The players that decided on second best in a round and could call it, as `Game::can_call_second_best` tells:
+// they had calls left and the colour to move had another turn to play
+fn could_call(rules: &RuleSet, earlier: &[Option<Colour>], colour: Colour, caller: Option<Colour>, turns: usize) -> Vec<Colour> {
+    if !rules.replay_vetoed && turns <= 1 {
+        return Vec::new();
+    }
+    let players = rules.board.players;
+    let mut deciders = Vec::new();
+    let mut decider = colour.next(players);
+    // The others decide in turn order until one of them calls
+    while decider != colour {
+        let used = earlier.iter().filter(|earlier| **earlier == Some(decider)).count();
+        if rules.second_best_calls.is_none_or(|calls| used < calls) {
+            deciders.push(decider);
+        }
+        if caller == Some(decider) {
+            break;
+        }
+        decider = decider.next(players);
+    }
+    deciders
+}
+
+fn analyse_turn(turn: &Turn, scored_turns: &[(Turn, f64)], vetoed: Option<&Turn>) -> TurnAnalysis {
+    let score = scored_turns
+        .iter()
+        .find(|(t, _)| t == turn)
+        .map(|(_, score)| *score)
+        .unwrap_or(f64::NEG_INFINITY);
+    let (best_turn, best_score) = scored_turns
+        .iter()
+        .filter(|(t, _)| Some(t) != vetoed)
+        .fold((turn.clone(), score), |best, (t, s)| {
+            if *s > best.1 {
+                (t.clone(), *s)
+            } else {
+                best
+            }
+        });
+    TurnAnalysis {
+        turn: turn.clone(),
+        score,
+        best_turn,
+        best_score,
+        judgement: Judgement::from_drop(best_score - score),
+    }
+}
+
+fn turn_annotation(analysis: &TurnAnalysis) -> String {
+    let mut annotation = format!(
+        "{}{} ({:+.1})",
+        notation::turn_to_string(&analysis.turn),
+        analysis.judgement.symbol(),
+        analysis.score
+    );
+    if analysis.judgement != Judgement::Good {
+        annotation += &format!(
+            ", best was {} ({:+.1})",
+            notation::turn_to_string(&analysis.best_turn),
+            analysis.best_score
+        );
+    }
+    annotation
+}
+
+// Write the analysis as a readable record, one round per line
+pub fn annotated_record(rounds: &[RoundAnalysis], players: usize) -> String {
+    let mut record = String::new();
+    for (i, round) in rounds.iter().enumerate() {
+        record += &format!("{}. {:?} {}", i + 1, round.colour, turn_annotation(&round.proposed));
+
+        let second_best = &round.second_best;
+        match (second_best.caller.is_some(), second_best.judgement) {
+            (true, Judgement::Good) => record += "; second best",
+            (true, judgement) => record += &format!("; second best{}, should have let it pass", judgement.symbol()),
+            (false, Judgement::Good) => {}
+            (false, judgement) => record += &format!("; no second best{}, should have called it", judgement.symbol()),
+        }
+
+        if let Some(second_turn) = &round.second_turn {
+            record += &format!(" -> {}", turn_annotation(second_turn));
+        }
+        record += "\n";
+    }
+    let mistakes = |colour: Colour, judgement: Judgement| {
+        rounds
+            .iter()
+            .flat_map(|round| {
+                let own = [Some(&round.proposed), round.second_turn.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .filter(|turn| round.colour == colour && turn.judgement == judgement)
+                    .count();
+                let charged = match round.second_best.caller {
+                    Some(caller) => caller == colour,
+                    None => round.second_best.could_call.contains(&colour),
+                };
+                let second_best = (charged && round.second_best.judgement == judgement) as usize;
+                [own, second_best]
+            })
+            .sum::<usize>()
+    };
+    for &colour in Colour::playing(players) {
+        record += &format!(
+            "{:?}: {} mistakes, {} blunders\n",
+            colour,
+            mistakes(colour, Judgement::Mistake),
+            mistakes(colour, Judgement::Blunder)
+        );
+    }
+    record
+}
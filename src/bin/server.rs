@@ -0,0 +1,30 @@
+use second_best::{rules::RuleSet, server};
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // Rules every table plays with, e.g. --rule stacks 10 --rule line 5
+    let mut rules = RuleSet::default();
+    while args.get(1).map(String::as_str) == Some("--rule") {
+        let (Some(name), Some(value)) = (args.get(2), args.get(3)) else {
+            return eprintln!("usage: server [--rule <name> <value>]... [port]");
+        };
+        if let Err(error) = rules.set(name, value) {
+            return eprintln!("{}", error);
+        }
+        args.drain(1..4);
+    }
+    if !rules.board.is_valid() {
+        return eprintln!("invalid board dimensions");
+    }
+
+    let port = match args.get(1).map(|port| port.parse::<u16>()) {
+        Some(Ok(port)) => port,
+        Some(Err(_)) => return eprintln!("usage: server [--rule <name> <value>]... [port]"),
+        None => server::DEFAULT_PORT,
+    };
+    println!("Second Best server listening on port {}", port);
+    if let Err(error) = server::run(port, rules) {
+        eprintln!("server failed: {}", error);
+    }
+}
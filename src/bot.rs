@@ -1,12 +1,138 @@
-use std::{rc::Rc, iter, borrow::Borrow};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+};
 
-use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
-use crate::{game::*, player::Player, io::IO};
+use crate::{
+    eval::{self, Weights},
+    game::*,
+    player::{Decision, Player},
+    rules::RuleSet,
+    search::{Bound, Entry, SearchState, SearchStats, TranspositionTable},
+};
+
+// Scores inside the search are whole hundredths of a point from the view of the colour to move,
+// a win is worth 100 points less a hundredth for every turn it takes to get there
+const SCALE: f64 = 100.0;
+const WIN: i32 = 10_000;
+// Scores beyond this are wins or losses
+const WIN_BOUND: i32 = WIN - 1_000;
+const INFINITY: i32 = WIN + 1;
+
+// Turns searched beyond the depth while a colour threatens to win
+const QUIESCENCE_DEPTH: u32 = 6;
+
+// Half the width of the first window a root turn is searched with around its previous score
+const ASPIRATION_WINDOW: i32 = 50;
+
+// Number of threads new bots search with, set once from the command line. Threads share the table
+// in whatever order they reach it, so searches on more than one are not reproducible from a seed
+static THREADS: AtomicUsize = AtomicUsize::new(1);
+
+pub fn set_threads(threads: usize) {
+    THREADS.store(threads.max(1), Ordering::Relaxed);
+}
+
+pub fn threads() -> usize {
+    THREADS.load(Ordering::Relaxed)
+}
+
+// Evaluation weights new bots use, set once from the command line
+static WEIGHTS: RwLock<Weights> = RwLock::new(Weights::STANDARD);
+
+pub fn set_weights(weights: Weights) {
+    *WEIGHTS.write().unwrap() = weights;
+}
+
+pub fn weights() -> Weights {
+    *WEIGHTS.read().unwrap()
+}
+
+// Where the seeds of new games and bots come from, seeded once from the command line to run
+// the same way again. Without a seed every run differs
+static SEEDS: Mutex<Option<StdRng>> = Mutex::new(None);
+
+pub fn set_seed(seed: u64) {
+    *SEEDS.lock().unwrap() = Some(StdRng::seed_from_u64(seed));
+}
+
+// A seed for a new game, the bots playing it take theirs from the game
+pub fn next_seed() -> u64 {
+    match SEEDS.lock().unwrap().as_mut() {
+        Some(seeds) => seeds.gen(),
+        None => rand::random(),
+    }
+}
+
+// The purposes seeds are derived from the seed of a game for, see `sub_seed`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Stream {
+    // The bot playing a colour in a round
+    Bot = 1,
+    // A game of self play in a training
+    TrainingGame = 2,
+    // The random turns of a game of self play
+    Exploration = 3,
+}
+
+// The seed of one of many streams of randomness taken from a seed. The seed, the purpose and the index
+// are mixed with splitmix64, so streams do not overlap the way seeds counting up from one another do
+pub fn sub_seed(seed: u64, stream: Stream, index: u64) -> u64 {
+    splitmix64(splitmix64(seed ^ splitmix64(stream as u64)) ^ index)
+}
+
+// Seed of bots not given one with `Bot::with_seed`, e.g. those giving hints and analyses.
+// They do not draw from `next_seed`, so they do not change the seeds of the games after them
+const HELPER_SEED: u64 = 0;
+
+fn splitmix64(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// Parse a thread count, "all" uses every core of the machine
+pub fn parse_threads(s: &str) -> Option<usize> {
+    match s {
+        "all" => Some(thread::available_parallelism().map_or(1, |threads| threads.get())),
+        _ => s.parse::<usize>().ok().filter(|threads| *threads >= 1),
+    }
+}
+
+// Scores of all turns of positions searched while the opponent was thinking, by the key of the
+// position and the depth they were searched to
+type Pondered = Mutex<HashMap<(u64, u64), Vec<(Turn, f64)>>>;
+
+// A search running in the background while the opponent thinks
+struct Ponder {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
 
 pub struct Bot {
     colour: Colour,
-    search_depth: u64
+    search_depth: u64,
+    stop: Option<Arc<AtomicBool>>,
+    rules: RuleSet,
+    threads: usize,
+    weights: Weights,
+    table: Arc<TranspositionTable>,
+    stats: Mutex<SearchStats>,
+    // Whether the search tries the turns most likely to be best first
+    ordering: bool,
+    pondering: bool,
+    ponder: Option<Ponder>,
+    pondered: Arc<Pondered>,
+    // Breaks ties between equally good turns
+    rng: Mutex<StdRng>,
 }
 
 impl Player for Bot {
@@ -14,24 +140,41 @@ impl Player for Bot {
         self.colour
     }
 
-    fn ask_put_piece(&self, board: &Board, second_best: bool) -> usize {
-        if let Turn::Place(_, i) = self.best_turn(board, second_best) {
-            return i;
-        } else {
-            panic!("Bot encoured invalid board state.")
-        }
+    fn ask_turn(&mut self, game: &Game) -> Decision {
+        self.stop_pondering();
+        self.forget_for_pondering();
+        let vetoed = match &game.phase {
+            Phase::Retry(vetoed) => Some(vetoed),
+            _ => None,
+        };
+        Decision::Play(self.choose_turn(&game.board, vetoed))
     }
 
-    fn ask_move_piece(&self, board: &Board, second_best: bool) -> (usize, usize) {
-        if let Turn::Move(_, i, j) = self.best_turn(board, second_best) {
-            return (i, j);
-        } else {
-            panic!("Bot encoured invalid board state.")
+    fn ask_second_best(&mut self, game: &Game, turn: &Turn) -> bool {
+        self.stop_pondering();
+        self.forget_for_pondering();
+        self.would_call_second_best(&game.board, turn)
+    }
+
+    fn turn_proposed(&mut self, _game: &Game, _turn: &Turn) {
+        self.stop_pondering();
+    }
+
+    // Think about the replies while the opponent is to move
+    fn turn_played(&mut self, game: &Game, _turn: &Turn) {
+        if game.phase == Phase::Propose && game.to_move() != self.colour {
+            self.start_pondering(&game.board);
         }
     }
 
-    fn ask_second_best(&self, board: &Board, turn: &Turn) -> bool {
-        self.best_turns_for_colour(board, self.colour.opposite(), false).contains(turn)
+    fn game_over(&mut self, _game: &Game, _outcome: &Outcome) {
+        self.stop_pondering();
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        self.stop_pondering();
     }
 }
 
@@ -39,21 +182,223 @@ impl Bot {
     pub fn new(colour: Colour, search_depth: u64) -> Self {
         Self { 
             colour,
-            search_depth
+            search_depth,
+            stop: None,
+            rules: RuleSet::default(),
+            threads: threads(),
+            weights: weights(),
+            table: Arc::new(TranspositionTable::new()),
+            stats: Mutex::new(SearchStats::default()),
+            ordering: true,
+            pondering: false,
+            ponder: None,
+            pondered: Arc::new(Mutex::new(HashMap::new())),
+            rng: Mutex::new(StdRng::seed_from_u64(HELPER_SEED)),
         }
     }
 
-    // Return vec of all turns with (second) best score for a colour
-    fn best_turns_for_colour(&self, board: &Board, colour: Colour, second_best: bool) -> Vec<Turn> {
-        // Create a vector of all possible turns
-        let binding = if board.count_pieces() < 16 {
-            Self::all_possible_place(colour, &board)
-        } else {
-            Self::all_possible_move(colour, &board)
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    // Search the turns at the root on several threads at once
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_weights(mut self, weights: Weights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    // Make the choices between equally good turns from a seed, e.g. `Game::bot_seed`
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    // Share the positions searched with other bots, e.g. the iterations of a deepening search
+    pub fn with_table(mut self, table: Arc<TranspositionTable>) -> Self {
+        self.table = table;
+        self
+    }
+
+    // The work done by all searches of this bot so far
+    pub fn stats(&self) -> SearchStats {
+        *self.stats.lock().unwrap()
+    }
+
+    // Let the search be aborted from another thread, scores are meaningless once stopped
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    // Search the turns in the order they are generated, e.g. to measure what ordering saves
+    pub fn with_ordering(mut self, ordering: bool) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    // Search ahead in the background while the opponent is to move, only in two player games
+    pub fn with_pondering(mut self, pondering: bool) -> Self {
+        self.pondering = pondering;
+        self
+    }
+
+    // Start searching the position the opponent has to move in, forgetting earlier pondering
+    fn start_pondering(&mut self, board: &Board) {
+        self.stop_pondering();
+        if !self.pondering || board.config.players != 2 {
+            return;
+        }
+        self.pondered.lock().unwrap().clear();
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut bot = Bot::new(self.colour, self.search_depth)
+            .with_rules(self.rules.clone())
+            .with_threads(self.threads)
+            .with_weights(self.weights)
+            .with_stop(stop.clone());
+        bot.pondered = self.pondered.clone();
+        let board = board.clone();
+        let handle = thread::spawn(move || bot.ponder(&board));
+        self.ponder = Some(Ponder { stop, handle });
+    }
+
+    // Whether a position was pondered depends on how long the opponent took. So that the turns do not,
+    // every search of a pondering bot starts from an empty table, as the pondering does
+    fn forget_for_pondering(&self) {
+        if self.pondering {
+            self.table.clear();
+        }
+    }
+
+    // Abort the background search, the positions it finished stay available
+    fn stop_pondering(&mut self) {
+        if let Some(ponder) = self.ponder.take() {
+            ponder.stop.store(true, Ordering::Relaxed);
+            let _ = ponder.handle.join();
+        }
+    }
+
+    // Score the turns of the opponent, which also decides whether to call second best on them,
+    // then the turns of the bot after every reply, the replies the opponent most likely plays first
+    fn ponder(&self, board: &Board) {
+        let opponent = self.colour.opposite();
+        let Some(mut replies) = self.ponder_turns(board, opponent) else {
+            return;
         };
+        replies.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        for (reply, _) in replies {
+            let mut new_board = board.clone();
+            new_board.do_turn(&reply);
+            if self.rules.is_won(&new_board, opponent).is_none() && self.ponder_turns(&new_board, self.colour).is_none() {
+                return;
+            }
+        }
+    }
+
+    // Score the turns of a position and keep them for when it comes up, None once pondering is stopped
+    fn ponder_turns(&self, board: &Board, colour: Colour) -> Option<Vec<(Turn, f64)>> {
+        self.table.clear();
+        let scores = self.score_turns(board, colour);
+        if self.is_stopped() {
+            return None;
+        }
+        let key = (TranspositionTable::key(board, colour), self.search_depth);
+        self.pondered.lock().unwrap().insert(key, scores.clone());
+        Some(scores)
+    }
+
+    // Return all possible turns for a colour with their score
+    pub fn score_turns(&self, board: &Board, colour: Colour) -> Vec<(Turn, f64)> {
+        // Positions already searched while pondering are not searched again
+        let key = (TranspositionTable::key(board, colour), self.search_depth);
+        if let Some(scores) = self.pondered.lock().unwrap().get(&key) {
+            return scores.clone();
+        }
+
+        // Create a vector of all possible turns
+        let binding = self.rules.possible_turns(board, colour);
+        if self.threads > 1 && binding.len() > 1 {
+            return self.score_turns_parallel(board, binding);
+        }
 
         // Score each move
-        let possible_turns: Vec<(Turn, f64)> = binding.into_iter().map(|turn| (turn.clone(), self.score_turn(&turn, board, colour, false, self.search_depth))).collect();
+        let mut state = SearchState::new(board.config);
+        let scores = binding.into_iter().map(|turn| (turn.clone(), self.score_root(&turn, board, &mut state))).collect();
+        self.stats.lock().unwrap().add(&state.stats);
+        scores
+    }
+
+    // Root splitting: the threads take the next unscored root turn until all are scored,
+    // sharing the transposition table
+    fn score_turns_parallel(&self, board: &Board, turns: Vec<Turn>) -> Vec<(Turn, f64)> {
+        let next = AtomicUsize::new(0);
+        let scores = Mutex::new(vec![0.0; turns.len()]);
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(turns.len()) {
+                scope.spawn(|| {
+                    let mut state = SearchState::new(board.config);
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(turn) = turns.get(idx) else {
+                            break;
+                        };
+                        let score = self.score_root(turn, board, &mut state);
+                        scores.lock().unwrap()[idx] = score;
+                    }
+                    self.stats.lock().unwrap().add(&state.stats);
+                });
+            }
+        });
+        turns.into_iter().zip(scores.into_inner().unwrap()).collect()
+    }
+
+    // Score a turn at the root of the search, from the view of the colour making it.
+    // Every root turn gets an exact score, so the second best turn is known as well as the best
+    fn score_root(&self, turn: &Turn, board: &Board, state: &mut SearchState) -> f64 {
+        if board.config.players > 2 {
+            return self.score_turn_paranoid(turn, board, turn.colour(), self.search_depth, state);
+        }
+        let depth = self.search_depth;
+        let mut new_board = board.clone();
+        new_board.do_turn(turn);
+        let key = TranspositionTable::key(&new_board, turn.colour().opposite());
+        // Aspiration: expect the score of an earlier search of the position, widen the window if it is wrong
+        let guess = match self.table.probe(key) {
+            Some(entry) if entry.bound == Bound::Exact && entry.score.abs() < WIN_BOUND => -entry.score,
+            _ => return self.search_turn(turn, board, depth, 0, -INFINITY, INFINITY, state) as f64 / SCALE,
+        };
+        let mut window = ASPIRATION_WINDOW;
+        loop {
+            let (alpha, beta) = (guess - window, guess + window);
+            let score = self.search_turn(turn, board, depth, 0, alpha, beta, state);
+            if (alpha < score && score < beta) || self.is_stopped() {
+                return score as f64 / SCALE;
+            }
+            state.stats.researches += 1;
+            if window >= WIN {
+                return self.search_turn(turn, board, depth, 0, -INFINITY, INFINITY, state) as f64 / SCALE;
+            }
+            window *= 4;
+        }
+    }
+
+    // Return the turns the bot recommends for a colour together with their score
+    pub fn recommend(&self, board: &Board, colour: Colour, second_best: bool) -> (Vec<Turn>, f64) {
+        let possible_turns = self.score_turns(board, colour);
+        match possible_turns.len() {
+            0 => return (Vec::new(), 0.0),
+            1 => return (vec![possible_turns[0].0.clone()], possible_turns[0].1),
+            _ => {}
+        }
 
         let mut best_moves: (Vec<Turn>, f64);
         let mut second_best_moves: (Vec<Turn>, f64);
@@ -64,9 +409,7 @@ impl Bot {
             best_moves = (vec![possible_turns[1].0.clone()], possible_turns[1].1);
             second_best_moves = (vec![possible_turns[0].0.clone()], possible_turns[0].1);
         }
-        for i in 2..possible_turns.len() {
-            let turn_with_score = &possible_turns[i];
-
+        for turn_with_score in possible_turns.iter().skip(2) {
             if turn_with_score.1 > best_moves.1 {
                 second_best_moves = best_moves;
                 best_moves = (vec![turn_with_score.0.clone()], turn_with_score.1);
@@ -79,75 +422,324 @@ impl Bot {
             }
         }
         if second_best {
-            second_best_moves.0
+            second_best_moves
         } else {
-            best_moves.0
+            best_moves
         }
     }
 
+    // Return whether second best should be called on a turn proposed by the opponent
+    pub fn would_call_second_best(&self, board: &Board, turn: &Turn) -> bool {
+        // Calling is pointless if the vetoed turn may simply be played again
+        !self.rules.replay_vetoed && self.recommend(board, turn.colour(), false).0.contains(turn)
+    }
+
     // Return the (second) best turn for the bot
     fn best_turn(&self, board: &Board, second_best: bool) -> Turn {
-        self.best_turns_for_colour(board, self.colour, second_best).choose(&mut rand::thread_rng()).unwrap().clone()
+        let second_best = second_best && !self.rules.replay_vetoed;
+        let turns = self.recommend(board, self.colour, second_best).0;
+        turns.choose(&mut *self.rng.lock().unwrap()).unwrap().clone()
+    }
+
+    // Return the turn the bot plays, after second best any turn other than the vetoed one
+    pub fn choose_turn(&self, board: &Board, vetoed: Option<&Turn>) -> Turn {
+        let vetoed = vetoed.filter(|_| !self.rules.replay_vetoed);
+        let turn = self.best_turn(board, vetoed.is_some());
+        if Some(&turn) != vetoed {
+            return turn;
+        }
+        self.score_turns(board, self.colour)
+            .into_iter()
+            .map(|(turn, _)| turn)
+            .find(|turn| Some(turn) != vetoed)
+            .unwrap_or(turn)
     }
 
-    fn score_turn(&self, turn: &Turn, board: &Board, colour: Colour, opponent: bool, depth: u64) -> f64 {
+    // Return the score of a single turn for a colour, positive is good for that colour
+    pub fn evaluate_turn(&self, board: &Board, turn: &Turn) -> f64 {
+        let mut state = SearchState::new(board.config);
+        let score = self.score_root(turn, board, &mut state);
+        self.stats.lock().unwrap().add(&state.stats);
+        score
+    }
+
+    // Return the expected line of play starting with a turn, each following turn being the best reply
+    pub fn principal_variation(&self, board: &Board, turn: &Turn) -> Vec<Turn> {
+        let mut pv = vec![turn.clone()];
         let mut new_board = board.clone();
         new_board.do_turn(turn);
+        let players = board.config.players;
+        let mut mover = turn.colour();
+        for depth in (0..self.search_depth).rev() {
+            if self.rules.is_won(&new_board, mover).is_some() || self.is_stopped() {
+                break;
+            }
+            let colour = mover.next(players);
+            let mut bot = Bot::new(colour, depth)
+                .with_rules(self.rules.clone())
+                .with_threads(self.threads)
+                .with_weights(self.weights)
+                .with_table(self.table.clone());
+            bot.stop = self.stop.clone();
+            let (turns, _) = bot.recommend(&new_board, colour, false);
+            match turns.first() {
+                Some(turn) => {
+                    new_board.do_turn(turn);
+                    pv.push(turn.clone());
+                }
+                None => break,
+            }
+            mover = colour;
+        }
+        pv
+    }
 
-        // If the board is in an endstate; return score based on it
-        if let Some(endstate) = new_board.is_won() {
-            (match endstate {
-                EndState::Win(Colour::Black) => {
-                    if colour == Colour::Black {
-                        100.0
-                    } else {
-                        -100.0
-                    }
-                },
-                EndState::Win(Colour::White) => {
-                    if colour == Colour::White {
-                        100.0
-                    } else {
-                        -100.0
-                    }
-                },
-                EndState::Draw => 0.0,
-            }) * (if opponent { -1.0 } else { 1.0 })
-        } else { // If not in an endstate; return 0 if searchdepth is reached, otherwise average score of next moves
-            if depth == 0 {
-                0.0
+    // Return the score of the board after a turn from the view of the colour making it,
+    // the replies are searched to one turn less than the depth
+    #[allow(clippy::too_many_arguments)]
+    fn search_turn(&self, turn: &Turn, board: &Board, depth: u64, ply: i32, alpha: i32, beta: i32, state: &mut SearchState) -> i32 {
+        let mover = turn.colour();
+        let mut new_board = board.clone();
+        new_board.do_turn(turn);
+        match self.rules.is_won(&new_board, mover) {
+            Some(EndState::Win(winner)) if winner == mover => WIN - ply,
+            Some(EndState::Win(_)) => -(WIN - ply),
+            Some(EndState::Draw) => 0,
+            None => -self.negamax(&new_board, mover.opposite(), depth, ply + 1, -beta, -alpha, state),
+        }
+    }
+
+    // Principal variation search of a board with a colour to move: the first turn is searched
+    // with the full window, the others only have to be shown worse with a null window
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(&self, board: &Board, colour: Colour, depth: u64, ply: i32, mut alpha: i32, mut beta: i32, state: &mut SearchState) -> i32 {
+        if self.is_stopped() {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiescence(board, colour, ply, alpha, beta, QUIESCENCE_DEPTH, state);
+        }
+        state.stats.nodes += 1;
+
+        let key = TranspositionTable::key(board, colour);
+        let entry = self.table.probe(key);
+        if let Some(entry) = entry.as_ref().filter(|entry| entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let (lower, upper) = match entry.bound {
+                Bound::Exact => (score, score),
+                Bound::Lower => (score, INFINITY),
+                Bound::Upper => (-INFINITY, score),
+            };
+            if lower >= beta || upper <= alpha || lower == upper {
+                state.stats.table_hits += 1;
+                return score;
+            }
+            alpha = alpha.max(lower);
+            beta = beta.min(upper);
+        }
+
+        let turns = self.rules.possible_turns(board, colour);
+        // A colour without a turn to play loses
+        if turns.is_empty() {
+            return -(WIN - ply);
+        }
+        let turns = if self.ordering {
+            self.order_turns(turns, board, ply, entry.and_then(|entry| entry.best), state)
+        } else {
+            turns
+        };
+
+        let original_alpha = alpha;
+        let mut best = (-INFINITY, None);
+        for (i, turn) in turns.iter().enumerate() {
+            let score = if i == 0 {
+                self.search_turn(turn, board, depth - 1, ply, alpha, beta, state)
             } else {
-                let binding = if new_board.count_pieces() < 16 {
-                    Self::all_possible_place(self.colour, &new_board)
+                let score = self.search_turn(turn, board, depth - 1, ply, alpha, alpha + 1, state);
+                if alpha < score && score < beta {
+                    state.stats.researches += 1;
+                    self.search_turn(turn, board, depth - 1, ply, score, beta, state)
                 } else {
-                    Self::all_possible_move(self.colour, &new_board)
-                };
-                binding.iter().map(|turn| self.score_turn(turn, &new_board, colour.opposite(), !opponent, depth - 1)).sum::<f64>() / (binding.len() as f64)
+                    score
+                }
+            };
+            if score > best.0 {
+                best = (score, Some(turn));
             }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                state.stats.cutoffs += 1;
+                if i == 0 {
+                    state.stats.first_cutoffs += 1;
+                }
+                state.add_cutoff(ply as usize, turn, depth);
+                break;
+            }
+        }
+        if self.is_stopped() {
+            return 0;
         }
+
+        let (score, best_turn) = best;
+        let bound = if score <= original_alpha {
+            Bound::Upper
+        } else if score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(
+            key,
+            Entry {
+                depth,
+                score: score_to_table(score, ply),
+                bound,
+                best: best_turn.cloned(),
+            },
+        );
+        score
     }
 
-    fn all_possible_place(colour: Colour, board: &Board) -> Vec<Turn> {
-        let mut turns: Vec<Turn> = vec![];
-        for i in 0..8 {
-            let turn = Turn::Place(colour, i);
-            if board.is_possible_turn(&turn) {
-                turns.push(turn);
+    // Beyond the depth only forcing turns are searched: a colour that can win does so, and a colour
+    // facing a win of its opponent has to block it. Other positions are quiet and get the heuristic score
+    #[allow(clippy::too_many_arguments)]
+    fn quiescence(&self, board: &Board, colour: Colour, ply: i32, mut alpha: i32, beta: i32, depth: u32, state: &mut SearchState) -> i32 {
+        if self.is_stopped() {
+            return 0;
+        }
+        state.stats.quiescence_nodes += 1;
+        let turns = self.rules.possible_turns(board, colour);
+        // A colour without a turn to play loses
+        if turns.is_empty() {
+            return -(WIN - ply);
+        }
+        if self.winning_turn(board, &turns).is_some() {
+            return WIN - ply;
+        }
+        let opponent = colour.opposite();
+        let threatened = self.winning_turn(board, &self.rules.possible_turns(board, opponent)).is_some();
+        if !threatened || depth == 0 {
+            return self.evaluate(board, colour);
+        }
+
+        // Every turn is searched, the replies tell whether it blocked the threat
+        let mut best = -INFINITY;
+        for turn in &turns {
+            let mut new_board = board.clone();
+            new_board.do_turn(turn);
+            let score = match self.rules.is_won(&new_board, colour) {
+                Some(EndState::Win(_)) => -(WIN - ply),
+                Some(EndState::Draw) => 0,
+                None => -self.quiescence(&new_board, opponent, ply + 1, -beta, -alpha, depth - 1, state),
+            };
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
             }
         }
-        turns
+        best
     }
 
-    fn all_possible_move(colour: Colour, board: &Board) -> Vec<Turn> {
-        let mut turns: Vec<Turn> = vec![];
-        for i in 0..8 {
-            for j in [1, 4, 7] {
-                let turn = Turn::Move(colour, i, (i + j) % 8);
-                if board.is_possible_turn(&turn) {
-                    turns.push(turn);
-                }
-            }
+    // Return a turn that wins the game for the colour making it
+    fn winning_turn<'a>(&self, board: &Board, turns: &'a [Turn]) -> Option<&'a Turn> {
+        turns.iter().find(|turn| {
+            let mut new_board = board.clone();
+            new_board.do_turn(turn);
+            self.rules.is_won(&new_board, turn.colour()) == Some(EndState::Win(turn.colour()))
+        })
+    }
+
+    // Search the turns most likely to be best first: wins, the best turn of an earlier search,
+    // turns that caused cutoffs elsewhere and turns creating threats
+    fn order_turns(&self, turns: Vec<Turn>, board: &Board, ply: i32, best: Option<Turn>, state: &SearchState) -> Vec<Turn> {
+        let colour = match turns.first() {
+            Some(turn) => turn.colour(),
+            None => return turns,
+        };
+        let threats = eval::threats(board, colour);
+        let mut scored: Vec<(u64, Turn)> = turns
+            .into_iter()
+            .map(|turn| {
+                let mut new_board = board.clone();
+                new_board.do_turn(&turn);
+                let priority = if self.rules.is_won(&new_board, colour) == Some(EndState::Win(colour)) {
+                    u64::MAX
+                } else if Some(&turn) == best.as_ref() {
+                    u64::MAX - 1
+                } else if state.is_killer(ply as usize, &turn) {
+                    u64::MAX - 2
+                } else if eval::threats(&new_board, colour) > threats {
+                    u64::MAX / 2 + state.history(&turn)
+                } else {
+                    state.history(&turn)
+                };
+                (priority, turn)
+            })
+            .collect();
+        // Stable, so equal turns keep their order
+        scored.sort_by_key(|(priority, _)| Reverse(*priority));
+        scored.into_iter().map(|(_, turn)| turn).collect()
+    }
+
+    // The heuristic score of a board for the colour to move, always short of a win
+    fn evaluate(&self, board: &Board, colour: Colour) -> i32 {
+        let score = (eval::evaluate(&self.weights, board, colour) * SCALE).round() as i32;
+        score.clamp(-WIN_BOUND + 1, WIN_BOUND - 1)
+    }
+
+    // With more than two players the search is paranoid: all other players are assumed to play
+    // against the root colour, which maximises its score while they minimise it
+    fn score_turn_paranoid(&self, turn: &Turn, board: &Board, root: Colour, depth: u64, state: &mut SearchState) -> f64 {
+        if self.is_stopped() {
+            return 0.0;
+        }
+        state.stats.nodes += 1;
+
+        let mover = turn.colour();
+        let mut new_board = board.clone();
+        new_board.do_turn(turn);
+        match self.rules.is_won(&new_board, mover) {
+            Some(EndState::Win(winner)) => return if winner == root { 100.0 } else { -100.0 },
+            Some(EndState::Draw) => return 0.0,
+            None => {}
+        }
+        if depth == 0 {
+            return 0.0;
         }
-        turns
+
+        let next = mover.next(board.config.players);
+        let turns = self.rules.possible_turns(&new_board, next);
+        // A player without a turn to play loses, the player before it wins
+        if turns.is_empty() {
+            return if mover == root { 100.0 } else { -100.0 };
+        }
+        let scores = turns.iter().map(|turn| self.score_turn_paranoid(turn, &new_board, root, depth - 1, state));
+        if next == root {
+            scores.fold(f64::MIN, f64::max)
+        } else {
+            scores.fold(f64::MAX, f64::min)
+        }
+    }
+}
+
+// Scores of wins are stored relative to the position, as the same position comes up at different distances from the root
+fn score_to_table(score: i32, ply: i32) -> i32 {
+    if score > WIN_BOUND {
+        score + ply
+    } else if score < -WIN_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: i32) -> i32 {
+    if score > WIN_BOUND {
+        score - ply
+    } else if score < -WIN_BOUND {
+        score + ply
+    } else {
+        score
     }
 }
@@ -0,0 +1,124 @@
+use std::fs;
+
+use crate::game::*;
+
+// Number of board features the evaluation is a weighted sum of
+pub const FEATURES: usize = 4;
+
+// Names of the features, in the order of `features`
+pub const FEATURE_NAMES: [&str; FEATURES] = ["tops", "pairs", "line_threats", "stack_threats"];
+
+// Points by which a score has to grow to make a win e times as likely as a loss
+pub const PROBABILITY_SCALE: f64 = 10.0;
+
+// Weights of the features, in points of the scores where a win is 100
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Weights(pub [f64; FEATURES]);
+
+impl Weights {
+    // Hand tuned, a threat to win is worth more than a good shape
+    pub const STANDARD: Weights = Weights([2.0, 3.0, 10.0, 8.0]);
+
+    // Parse weights written by `to_text`, one "<feature> <weight>" per line,
+    // features that are left out keep their standard weight
+    pub fn parse(text: &str) -> Result<Weights, String> {
+        let mut weights = Weights::STANDARD;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (name, value) = line.split_once(char::is_whitespace).ok_or_else(|| format!("invalid line {}", line))?;
+            let idx = FEATURE_NAMES
+                .iter()
+                .position(|feature| *feature == name)
+                .ok_or_else(|| format!("unknown feature {}", name))?;
+            weights.0[idx] = value.trim().parse::<f64>().map_err(|_| format!("invalid weight {}", value))?;
+        }
+        Ok(weights)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# second best evaluation weights\n");
+        for (name, weight) in FEATURE_NAMES.iter().zip(self.0.iter()) {
+            text.push_str(&format!("{} {}\n", name, weight));
+        }
+        text
+    }
+
+    pub fn load(path: &str) -> Result<Weights, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("could not read {}: {}", path, error))?;
+        Weights::parse(&text).map_err(|error| format!("{} is not a weights file: {}", path, error))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|error| format!("could not write {}: {}", path, error))
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+// Return the score of a board for a colour, positive is good for that colour
+pub fn evaluate(weights: &Weights, board: &Board, colour: Colour) -> f64 {
+    features(board, colour)
+        .iter()
+        .zip(weights.0.iter())
+        .map(|(feature, weight)| feature * weight)
+        .sum()
+}
+
+// Return how likely a score makes a win, counting a draw as half a win
+pub fn win_probability(score: f64) -> f64 {
+    1.0 / (1.0 + (-score / PROBABILITY_SCALE).exp())
+}
+
+// Return the features of a board for a colour, each is the count for the colour
+// minus the average count of the other colours
+pub fn features(board: &Board, colour: Colour) -> [f64; FEATURES] {
+    let colours = Colour::playing(board.config.players);
+    let mut features = [0.0; FEATURES];
+    for other in colours {
+        let counts = counts(board, *other);
+        let weight = if *other == colour {
+            1.0
+        } else {
+            -1.0 / (colours.len() - 1) as f64
+        };
+        for (feature, count) in features.iter_mut().zip(counts) {
+            *feature += weight * count as f64;
+        }
+    }
+    features
+}
+
+// Return the number of patterns of a colour: stacks with the colour on top, neighbouring stacks
+// with the colour on top, lines one top short of a win and stacks one piece short of a win
+pub fn counts(board: &Board, colour: Colour) -> [usize; FEATURES] {
+    let stacks = board.places.len();
+    let line = board.config.line;
+    let on_top: Vec<bool> = board.places.iter().map(|place| *place.peek_top() == Piece::Piece(colour)).collect();
+
+    let tops = on_top.iter().filter(|top| **top).count();
+    let pairs = (0..stacks).filter(|&i| on_top[i] && on_top[(i + 1) % stacks]).count();
+    let line_threats = (0..stacks)
+        .filter(|&i| (0..line).filter(|j| on_top[(i + j) % stacks]).count() == line - 1)
+        .count();
+    let stack_threats = threat_stacks(board, colour).count();
+    [tops, pairs, line_threats, stack_threats]
+}
+
+// Return the stacks a colour fills with its own pieces by adding one more
+fn threat_stacks(board: &Board, colour: Colour) -> impl Iterator<Item = usize> + '_ {
+    let height = board.config.height;
+    board.places.iter().enumerate().filter_map(move |(i, place)| {
+        let count = place.count_pieces();
+        let own = place.0[..count].iter().all(|piece| *piece == Piece::Piece(colour));
+        (count == height - 1 && own).then_some(i)
+    })
+}
+
+// Return the number of patterns a colour is one turn away from completing
+pub fn threats(board: &Board, colour: Colour) -> usize {
+    let counts = counts(board, colour);
+    counts[2] + counts[3]
+}
@@ -0,0 +1,211 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    game::*,
+    notation,
+    player::{Decision, Player},
+    rules::RuleSet,
+};
+
+// Extra time an engine gets on top of its move time before it is considered to have timed out
+const GRACE_TIME: Duration = Duration::from_secs(2);
+
+// Time an engine gets to start up and answer the handshake
+const STARTUP_TIME: Duration = Duration::from_secs(10);
+
+const TIMED_OUT: &str = "engine timed out";
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+// A player backed by an engine executable speaking the protocol of `protocol`.
+// If the engine crashes, times out or answers with an illegal turn it forfeits the game.
+pub struct ExternalEngine {
+    colour: Colour,
+    move_time: Duration,
+    process: Option<Process>,
+    forfeit_reason: Option<String>,
+}
+
+impl ExternalEngine {
+    // Start the engine, wait for it to complete the handshake and tell it the rules of the game
+    pub fn launch(colour: Colour, command: &str, move_time: Duration, rules: &RuleSet) -> io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // Read lines on a separate thread so waiting for the engine can time out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                }
+            }
+        });
+
+        let mut engine = Self {
+            colour,
+            move_time,
+            process: Some(Process { child, stdin, lines }),
+            forfeit_reason: None,
+        };
+        engine.send("sbp");
+        engine.wait_for(STARTUP_TIME, |line| (line == "sbpok").then_some(()));
+        engine.send(&format!("rules {}", rules.settings_line()));
+        engine.send("isready");
+        engine.wait_for(STARTUP_TIME, |line| (line == "readyok").then_some(()));
+        match engine.forfeit_reason.take() {
+            Some(reason) => Err(io::Error::other(reason)),
+            None => Ok(engine),
+        }
+    }
+
+    fn forfeit(&mut self, reason: String) {
+        if self.forfeit_reason.is_none() {
+            self.forfeit_reason = Some(reason);
+        }
+        if let Some(mut process) = self.process.take() {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+
+    fn send(&mut self, line: &str) {
+        let result = match self.process.as_mut() {
+            Some(process) => writeln!(process.stdin, "{}", line).and_then(|_| process.stdin.flush()),
+            None => return,
+        };
+        if let Err(error) = result {
+            self.forfeit(format!("engine could not be reached: {}", error));
+        }
+    }
+
+    // Read lines until one is accepted by the parser, forfeit if the engine takes too long or exits
+    fn wait_for<T>(&mut self, timeout: Duration, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+        loop {
+            match self.process.as_ref()?.lines.recv_timeout(timeout) {
+                Ok(line) => {
+                    if let Some(value) = parse(line.trim()) {
+                        return Some(value);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.forfeit(TIMED_OUT.to_owned());
+                    return None;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.forfeit("engine exited".to_owned());
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn set_position(&mut self, board: &Board, to_move: Colour) {
+        self.send(&format!(
+            "position board {} {}",
+            notation::board_to_string(board),
+            notation::colour_to_char(to_move)
+        ));
+    }
+
+    // Ask the engine for a turn, a turn after second best may not repeat the vetoed one
+    fn request_turn(&mut self, game: &Game) -> Option<Turn> {
+        self.set_position(&game.board, self.colour);
+        match &game.phase {
+            Phase::Retry(vetoed) => self.send(&format!(
+                "go movetime {} vetoed {}",
+                self.move_time.as_millis(),
+                notation::turn_to_string(vetoed)
+            )),
+            _ => self.send(&format!("go movetime {}", self.move_time.as_millis())),
+        }
+        let reply = self.wait_for(self.move_time + GRACE_TIME, |line| {
+            line.strip_prefix("bestmove ").map(|turn| turn.trim().to_owned())
+        })?;
+        match notation::parse_turn(&reply, self.colour) {
+            Some(turn) if game.legal_turns().contains(&turn) => Some(turn),
+            _ => {
+                self.forfeit(format!("engine played illegal turn {}", reply));
+                None
+            }
+        }
+    }
+}
+
+impl Player for ExternalEngine {
+    fn get_colour(&self) -> Colour {
+        self.colour
+    }
+
+    // An engine that fails to answer forfeits, the turn returned then is never played
+    fn ask_turn(&mut self, game: &Game) -> Decision {
+        match self.request_turn(game) {
+            Some(turn) => Decision::Play(turn),
+            None => Decision::Resign,
+        }
+    }
+
+    fn ask_second_best(&mut self, game: &Game, turn: &Turn) -> bool {
+        self.set_position(&game.board, turn.colour());
+        self.send(&format!("secondbest? {}", notation::turn_to_string(turn)));
+        self.wait_for(self.move_time + GRACE_TIME, |line| match line {
+            "secondbest yes" => Some(true),
+            "secondbest no" => Some(false),
+            _ => None,
+        })
+        .unwrap_or(false)
+    }
+
+    fn forfeit_reason(&self) -> Option<String> {
+        self.forfeit_reason.clone()
+    }
+
+    fn forfeit_end_reason(&self) -> EndReason {
+        match self.forfeit_reason.as_deref() {
+            Some(TIMED_OUT) => EndReason::Timeout,
+            _ => EndReason::Forfeit,
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        self.send("quit");
+        if let Some(mut process) = self.process.take() {
+            // Give the engine a moment to quit on its own before killing it
+            for _ in 0..10 {
+                if let Ok(Some(_)) = process.child.try_wait() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
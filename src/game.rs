@@ -1,97 +1,123 @@
+use crate::analysis;
 use crate::bot::*;
+use crate::external::ExternalEngine;
 use crate::io::*;
+use crate::network::{self, Connection, NetworkedPlayer, RemotePlayer};
+use crate::notation;
 use crate::player::Person;
-use crate::player::Player;
+use crate::player::{Decision, Player};
+use crate::rules::RuleSet;
 
 use core::panic;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+// Time an external engine gets to think about each turn
+const ENGINE_MOVE_TIME: Duration = Duration::from_secs(1);
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum Colour {
     White,
     Black,
+    // Only plays in the three player variant
+    Red,
 }
 
 impl Colour {
+    // The colours in turn order
+    pub const ALL: [Colour; 3] = [Colour::White, Colour::Black, Colour::Red];
+
+    // The opponent in a two player game, Red only plays with three players and has no single opponent
     pub fn opposite(&self) -> Colour {
-        if *self == Colour::White {
-            Colour::Black
-        } else {
-            Colour::White
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+            Colour::Red => panic!("Red has no opposite colour, only two player games have one"),
         }
     }
+
+    // The colour moving after this one in a game with the given number of players
+    pub fn next(&self, players: usize) -> Colour {
+        let idx = Colour::ALL.iter().position(|colour| colour == self).unwrap_or(0);
+        Colour::ALL[(idx + 1) % players]
+    }
+
+    // The colours taking part in a game with the given number of players
+    pub fn playing(players: usize) -> &'static [Colour] {
+        &Colour::ALL[..players]
+    }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+// A piece is written as its colour, a blank as null
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(untagged))]
 pub enum Piece {
     Piece(Colour),
     Blank,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-pub struct Place(pub [Piece; 3]);
+// A stack of pieces from the bottom up, the free slots on top are blank
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Place(pub Vec<Piece>);
 
 impl Place {
     pub fn new() -> Self {
-        Self([Piece::Blank, Piece::Blank, Piece::Blank])
+        Self::with_height(BoardConfig::STANDARD.height)
+    }
+
+    pub fn with_height(height: usize) -> Self {
+        Self(vec![Piece::Blank; height])
     }
 
     pub fn is_full(&self) -> bool {
-        match &self.0 {
-            [Piece::Piece(_), Piece::Piece(_), Piece::Piece(_)] => true,
-            _ => false,
-        }
+        self.0.last().is_none_or(|piece| *piece != Piece::Blank)
     }
 
     pub fn is_empty(&self) -> bool {
-        match &self.0 {
-            [Piece::Blank, Piece::Blank, Piece::Blank] => true,
-            _ => false,
-        }
+        self.0.first().is_none_or(|piece| *piece == Piece::Blank)
     }
 
     pub fn peek_top(&self) -> &Piece {
-        match &self.0 {
-            [Piece::Blank, Piece::Blank, Piece::Blank] => &Piece::Blank,
-            [top @ Piece::Piece(_), Piece::Blank, Piece::Blank] => top,
-            [Piece::Piece(_), top @ Piece::Piece(_), Piece::Blank] => top,
-            [Piece::Piece(_), Piece::Piece(_), top @ Piece::Piece(_)] => top,
-            _ => panic!("Board is invalid at {:?}", self),
+        let count = self.count_pieces();
+        if self.0[count..].iter().any(|piece| *piece != Piece::Blank) {
+            panic!("Board is invalid at {:?}", self);
+        }
+        match count {
+            0 => &Piece::Blank,
+            _ => &self.0[count - 1],
         }
     }
 
+    // Pieces are stacked without gaps, so the pieces are the slots before the first blank
     pub fn count_pieces(&self) -> usize {
-        let mut total = 0;
-        for i in 0..3 {
-            if self.0[i] != Piece::Blank {
-                total += 1;
-            };
+        self.0.iter().take_while(|piece| **piece != Piece::Blank).count()
+    }
+
+    // The colour of a full stack with pieces of only one colour
+    pub fn single_colour(&self) -> Option<Colour> {
+        match self.0.first()? {
+            Piece::Piece(colour) if self.0.iter().all(|piece| *piece == Piece::Piece(*colour)) => Some(*colour),
+            _ => None,
         }
-        total
     }
 
     pub fn add_piece(&mut self, colour: &Colour) {
-        match &self.0 {
-            [Piece::Blank, Piece::Blank, Piece::Blank] => self.0[0] = Piece::Piece(*colour),
-            [Piece::Piece(_), Piece::Blank, Piece::Blank] => self.0[1] = Piece::Piece(*colour),
-            [Piece::Piece(_), Piece::Piece(_), Piece::Blank] => self.0[2] = Piece::Piece(*colour),
-            _ => panic!(
+        let count = self.count_pieces();
+        if count == self.0.len() {
+            panic!(
                 "Adding piece is invalid move, or board is invalid at {:?}",
                 self
-            ),
+            );
         }
+        self.0[count] = Piece::Piece(*colour);
     }
 
     pub fn remove_piece(&mut self, colour: &Colour) {
-        match &self.0 {
-            [Piece::Piece(c), Piece::Blank, Piece::Blank] if *c == *colour => {
-                self.0[0] = Piece::Blank
-            }
-            [Piece::Piece(_), Piece::Piece(c), Piece::Blank] if *c == *colour => {
-                self.0[1] = Piece::Blank
-            }
-            [Piece::Piece(_), Piece::Piece(_), Piece::Piece(c)] if *c == *colour => {
-                self.0[2] = Piece::Blank
-            }
+        match self.count_pieces() {
+            count @ 1.. if self.0[count - 1] == Piece::Piece(*colour) => self.0[count - 1] = Piece::Blank,
             _ => panic!(
                 "Removing piece is invalid move, or board is invalid at {:?}",
                 self
@@ -100,208 +126,774 @@ impl Place {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+impl Default for Place {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum EndState {
     Win(Colour),
     Draw,
 }
 
-#[derive(Clone)]
-pub struct Board(pub [Place; 8]);
+// Why a game ended
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum EndReason {
+    // A stack full of one colour
+    Stack,
+    // Four consecutive stacks with the same colour on top
+    RingLine,
+    // A turn created winning patterns for both colours
+    Simultaneous,
+    // The same position came up for the third time
+    Repetition,
+    Resignation,
+    Timeout,
+    // The side to move had no possible turn
+    NoLegalMove,
+    // A player could not continue, e.g. a crashed engine or a misbehaving peer
+    Forfeit,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    pub result: EndState,
+    pub reason: EndReason,
+    // The stacks forming the winning patterns, empty if the game did not end on the board
+    pub stacks: Vec<usize>,
+}
+
+impl Outcome {
+    pub fn new(result: EndState, reason: EndReason) -> Self {
+        Self {
+            result,
+            reason,
+            stacks: Vec::new(),
+        }
+    }
+}
+
+// Boards larger than this cannot be written in the notation of `notation`
+pub const MAX_STACKS: usize = 32;
+
+// The dimensions of a board
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct BoardConfig {
+    // Number of stacks in the ring
+    pub stacks: usize,
+    // Number of pieces a stack holds
+    pub height: usize,
+    // Number of consecutive stacks with the same colour on top that wins
+    pub line: usize,
+    // Pieces per colour, all of them are placed before pieces are moved
+    pub pieces: usize,
+    // Number of colours playing, 2 or 3
+    pub players: usize,
+}
+
+impl BoardConfig {
+    // A ring of 8 stacks of 3, four in a row wins and each colour has 8 pieces
+    pub const STANDARD: BoardConfig = BoardConfig {
+        stacks: 8,
+        height: 3,
+        line: 4,
+        pieces: 8,
+        players: 2,
+    };
+
+    // The three player variant on a ring of 12 stacks, each colour has 8 pieces
+    pub const THREE_PLAYER: BoardConfig = BoardConfig {
+        stacks: 12,
+        height: 3,
+        line: 4,
+        pieces: 8,
+        players: 3,
+    };
+
+    // There has to be room left to move once all pieces are placed
+    pub fn is_valid(&self) -> bool {
+        (3..=MAX_STACKS).contains(&self.stacks)
+            && self.height >= 1
+            && (2..=self.stacks).contains(&self.line)
+            && self.pieces >= 1
+            && (2..=Colour::ALL.len()).contains(&self.players)
+            && self.players * self.pieces < self.stacks * self.height
+    }
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "crate::schema::BoardData"))]
+pub struct Board {
+    pub places: Vec<Place>,
+    pub config: BoardConfig,
+}
 
 impl Board {
     pub fn new() -> Self {
-        Self([
-            Place::new(), Place::new(), Place::new(), Place::new(),
-            Place::new(), Place::new(), Place::new(), Place::new(),
-        ])
+        Self::with_config(BoardConfig::STANDARD)
+    }
+
+    pub fn with_config(config: BoardConfig) -> Self {
+        Self {
+            places: vec![Place::with_height(config.height); config.stacks],
+            config,
+        }
     }
 
     pub fn count_pieces(&self) -> usize {
-        let mut total = 0;
-        for i in 0..8 {
-            total += self.0[i].count_pieces();
+        self.places.iter().map(Place::count_pieces).sum()
+    }
+
+    // Pieces are placed until all of them are on the board, after that they are moved
+    pub fn is_placing(&self) -> bool {
+        self.count_pieces() < self.config.players * self.config.pieces
+    }
+
+    // Return the number of steps between two stacks, going around the ring the shorter way
+    pub fn ring_distance(&self, idx1: usize, idx2: usize) -> usize {
+        let distance = idx1.abs_diff(idx2) % self.places.len();
+        distance.min(self.places.len() - distance)
+    }
+
+    // Return the stacks of the line of tops starting at a stack
+    fn line(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.config.line).map(move |j| (start + j) % self.places.len())
+    }
+
+    // Return the colours with a winning pattern on the board
+    pub fn winners(&self) -> Vec<Colour> {
+        let mut winners = Vec::new();
+        let mut add = |colour: Colour| {
+            if !winners.contains(&colour) {
+                winners.push(colour);
+            }
+        };
+        for i in 0..self.places.len() {
+            if let Some(colour) = self.places[i].single_colour() {
+                add(colour);
+            }
+            if let Piece::Piece(colour) = self.places[i].peek_top() {
+                if self.line(i).all(|j| self.places[j].peek_top() == &Piece::Piece(*colour)) {
+                    add(*colour);
+                }
+            }
         }
-        total
+        winners
     }
 
     pub fn is_won(&self) -> Option<EndState> {
-        let mut black_win = false;
-        let mut white_win = false;
-        for i in 0..8 {
-            match &self.0[i].0 {
-                [Piece::Piece(Colour::Black), Piece::Piece(Colour::Black), Piece::Piece(Colour::Black)] => {
-                    black_win = true
-                }
-                [Piece::Piece(Colour::White), Piece::Piece(Colour::White), Piece::Piece(Colour::White)] => {
-                    white_win = true
-                }
-                _ => {}
+        match self.winners().as_slice() {
+            [] => None,
+            [colour] => Some(EndState::Win(*colour)),
+            _ => Some(EndState::Draw),
+        }
+    }
+
+    // Return how the board ends the game, with the stacks forming the winning patterns
+    pub fn outcome(&self) -> Option<Outcome> {
+        let mut patterns: Vec<(Colour, EndReason, Vec<usize>)> = Vec::new();
+        for i in 0..self.places.len() {
+            if let Some(colour) = self.places[i].single_colour() {
+                patterns.push((colour, EndReason::Stack, vec![i]));
             }
-            match [
-                &self.0[i].peek_top(),
-                &self.0[(i + 1) % 8].peek_top(),
-                &self.0[(i + 2) % 8].peek_top(),
-                &self.0[(i + 3) % 8].peek_top(),
-            ] {
-                [&Piece::Piece(Colour::Black), &Piece::Piece(Colour::Black), 
-                 &Piece::Piece(Colour::Black), &Piece::Piece(Colour::Black)] => {
-                    black_win = true
-                }
-                [&Piece::Piece(Colour::White), &Piece::Piece(Colour::White), 
-                 &Piece::Piece(Colour::White), &Piece::Piece(Colour::White)] => {
-                    white_win = true
+            if let Piece::Piece(colour) = self.places[i].peek_top() {
+                let line: Vec<usize> = self.line(i).collect();
+                if line.iter().all(|&j| self.places[j].peek_top() == &Piece::Piece(*colour)) {
+                    patterns.push((*colour, EndReason::RingLine, line));
                 }
-                _ => {}
             }
         }
-        match (black_win, white_win) {
-            (true, true) => Some(EndState::Draw),
-            (true, false) => Some(EndState::Win(Colour::Black)),
-            (false, true) => Some(EndState::Win(Colour::White)),
-            (false, false) => None,
-        }
+        let (first_colour, _, _) = patterns.first()?;
+        let mut stacks: Vec<usize> = patterns.iter().flat_map(|(_, _, stacks)| stacks.clone()).collect();
+        stacks.sort();
+        stacks.dedup();
+        let (result, reason) = if patterns.iter().any(|(colour, _, _)| colour != first_colour) {
+            (EndState::Draw, EndReason::Simultaneous)
+        } else if patterns.iter().any(|(_, reason, _)| *reason == EndReason::Stack) {
+            (EndState::Win(*first_colour), EndReason::Stack)
+        } else {
+            (EndState::Win(*first_colour), EndReason::RingLine)
+        };
+        Some(Outcome {
+            result,
+            reason,
+            stacks,
+        })
     }
 
+    // A piece moves to a neighbouring stack or the opposite one on a ring with an even number of stacks
     pub fn is_possible_turn(&self, turn: &Turn) -> bool {
+        let stacks = self.places.len();
         match *turn {
-            Turn::Place(_, idx) => !self.0[idx].is_full(),
-            Turn::Move(colour, idx1, idx2) => {
-                if let Piece::Piece(c) = self.0[idx1].peek_top() {
-                    !self.0[idx2].is_full()
-                        && *c == colour
-                        && (idx1 != idx2)
-                        && (((idx1.abs_diff(idx2) % 8) == 1)
-                            || ((idx1.abs_diff(idx2) % 8) == 7)
-                            || ((idx1.abs_diff(idx2) % 8) == 4))
-                } else {
-                    false
-                }
+            Turn::Move(_, idx1, idx2) if idx1 < stacks && idx2 < stacks => {
+                let distance = self.ring_distance(idx1, idx2);
+                self.fits_stacks(turn) && (distance == 1 || (stacks.is_multiple_of(2) && distance == stacks / 2))
             }
+            _ => self.fits_stacks(turn),
+        }
+    }
+
+    // Whether a turn fits the stacks, the distance of a move is left to the caller
+    pub fn fits_stacks(&self, turn: &Turn) -> bool {
+        match *turn {
+            Turn::Place(_, idx) => self.places.get(idx).is_some_and(|place| !place.is_full()),
+            Turn::Move(colour, idx1, idx2) => match (self.places.get(idx1), self.places.get(idx2)) {
+                (Some(from), Some(to)) => {
+                    idx1 != idx2 && !to.is_full() && *from.peek_top() == Piece::Piece(colour)
+                }
+                _ => false,
+            },
         }
     }
 
     pub fn do_turn(&mut self, turn: &Turn) {
         match *turn {
             Turn::Place(colour, idx) => {
-                self.0[idx].add_piece(&colour);
+                self.places[idx].add_piece(&colour);
             }
             Turn::Move(colour, idx1, idx2) => {
-                self.0[idx1].remove_piece(&colour);
-                self.0[idx2].add_piece(&colour);
+                self.places[idx1].remove_piece(&colour);
+                self.places[idx2].add_piece(&colour);
+            }
+        }
+    }
+
+    // Take back a turn that was the last one done on the board
+    pub fn undo_turn(&mut self, turn: &Turn) {
+        match *turn {
+            Turn::Place(colour, idx) => {
+                self.places[idx].remove_piece(&colour);
+            }
+            Turn::Move(colour, idx1, idx2) => {
+                self.places[idx2].remove_piece(&colour);
+                self.places[idx1].add_piece(&colour);
             }
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(from = "crate::schema::TurnData", into = "crate::schema::TurnData"))]
 pub enum Turn {
     Place(Colour, usize),
     Move(Colour, usize, usize),
 }
 
+impl Turn {
+    pub fn colour(&self) -> Colour {
+        match *self {
+            Turn::Place(colour, _) | Turn::Move(colour, _, _) => colour,
+        }
+    }
+}
+
+// A round of the game: the turn first proposed and, if second best was called, the turn played instead
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Round {
+    pub proposed: Turn,
+    pub second_turn: Option<Turn>,
+}
+
+impl Round {
+    pub fn played(&self) -> &Turn {
+        self.second_turn.as_ref().unwrap_or(&self.proposed)
+    }
+}
+
+// What a game driven turn by turn is waiting for
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Phase {
+    // The side to move proposes a turn
+    Propose,
+    // The opponent decides whether to call second best on the proposed turn
+    SecondBest(Turn),
+    // Second best was called, the side to move plays another turn than the vetoed one
+    Retry(Turn),
+    Over(Outcome),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum GameError {
+    GameOver,
+    NotYourTurn,
+    IllegalTurn,
+    NoSecondBestPending,
+    // No calls left, or the side to move has no other turn to play
+    SecondBestNotAllowed,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::GameOver => write!(f, "the game is over"),
+            GameError::NotYourTurn => write!(f, "it is not your turn"),
+            GameError::IllegalTurn => write!(f, "that turn is not possible"),
+            GameError::NoSecondBestPending => write!(f, "there is no turn to call second best on"),
+            GameError::SecondBestNotAllowed => write!(f, "second best cannot be called on this turn"),
+        }
+    }
+}
+
 pub struct Game {
     pub board: Board,
     pub turns: Vec<Turn>,
+    pub rounds: Vec<Round>,
+    pub phase: Phase,
+    pub rules: RuleSet,
+    // Seed of the bots playing the game, the same seed and the same turns of the other players
+    // make the bots play the same game again as long as they search on a single thread
+    pub seed: u64,
+    // The board after every round, to detect repetitions
+    positions: Vec<Board>,
+    // The colour that called second best in every round
+    callers: Vec<Option<Colour>>,
+    // The number of players that let the proposed turn pass so far
+    passes: usize,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
+        Self::with_rules(RuleSet::default())
+    }
+
+    pub fn with_rules(rules: RuleSet) -> Self {
         Self {
-            board: Board::new(),
+            board: Board::with_config(rules.board),
             turns: Vec::new(),
+            rounds: Vec::new(),
+            phase: Phase::Propose,
+            rules,
+            seed: next_seed(),
+            positions: Vec::new(),
+            callers: Vec::new(),
+            passes: 0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // Replay recorded rounds, every turn is checked against the rules
+    pub fn from_rounds(rounds: &[Round]) -> Result<Self, GameError> {
+        Self::from_rounds_with_rules(rounds, RuleSet::default())
+    }
+
+    pub fn from_rounds_with_rules(rounds: &[Round], rules: RuleSet) -> Result<Self, GameError> {
+        let mut game = Self::with_rules(rules);
+        for round in rounds {
+            game.propose_turn(round.proposed.clone())?;
+            // A record does not say who called second best, with three players it is taken to be the first to decide
+            while let (Phase::SecondBest(_), Some(colour)) = (&game.phase, game.waiting_for()) {
+                game.decide_second_best(colour, round.second_turn.is_some())?;
+            }
+            if let Some(second_turn) = &round.second_turn {
+                game.retry_turn(second_turn.clone())?;
+            }
+        }
+        Ok(game)
+    }
+
+    // The seed of a bot playing a colour from the current round on,
+    // bots made for a single turn get a different one every round
+    pub fn bot_seed(&self, colour: Colour) -> u64 {
+        let idx = Colour::ALL.iter().position(|c| *c == colour).unwrap_or(0);
+        sub_seed(self.seed, Stream::Bot, (self.rounds.len() * Colour::ALL.len() + idx) as u64)
+    }
+
+    // The colour making a turn in the current round
+    pub fn to_move(&self) -> Colour {
+        Colour::ALL[self.rounds.len() % self.players()]
+    }
+
+    pub fn players(&self) -> usize {
+        self.rules.board.players
+    }
+
+    // The colour the game is waiting for, if it is not over.
+    // The other players decide on second best one after the other in turn order.
+    pub fn waiting_for(&self) -> Option<Colour> {
+        match self.phase {
+            Phase::Propose | Phase::Retry(_) => Some(self.to_move()),
+            Phase::SecondBest(_) => Some(self.deciding()),
+            Phase::Over(_) => None,
+        }
+    }
+
+    fn deciding(&self) -> Colour {
+        (0..=self.passes).fold(self.to_move(), |colour, _| colour.next(self.players()))
+    }
+
+    // Return the turns the side to move may play now, after second best without the vetoed turn
+    pub fn legal_turns(&self) -> Vec<Turn> {
+        match &self.phase {
+            Phase::Propose => self.rules.possible_turns(&self.board, self.to_move()),
+            Phase::Retry(vetoed) => self
+                .rules
+                .possible_turns(&self.board, self.to_move())
+                .into_iter()
+                .filter(|turn| turn != vetoed || self.rules.replay_vetoed)
+                .collect(),
+            Phase::SecondBest(_) | Phase::Over(_) => Vec::new(),
+        }
+    }
+
+    // A turn has to match the phase of the game and be possible on the board
+    fn is_legal_turn(&self, turn: &Turn) -> bool {
+        let placing = self.board.is_placing();
+        placing == matches!(turn, Turn::Place(..)) && self.rules.is_possible_turn(&self.board, turn)
+    }
+
+    // Propose the turn for the side to move, the opponent then decides on second best
+    pub fn propose_turn(&mut self, turn: Turn) -> Result<(), GameError> {
+        match self.phase {
+            Phase::Propose => {}
+            Phase::Over(_) => return Err(GameError::GameOver),
+            _ => return Err(GameError::NotYourTurn),
+        }
+        if turn.colour() != self.to_move() {
+            return Err(GameError::NotYourTurn);
+        }
+        if !self.is_legal_turn(&turn) {
+            return Err(GameError::IllegalTurn);
+        }
+        self.phase = Phase::SecondBest(turn);
+        Ok(())
+    }
+
+    // Let an opponent call second best on the proposed turn, the turn is played once all of them let it pass.
+    // A call is refused unless `can_call_second_best` allows it
+    pub fn decide_second_best(&mut self, colour: Colour, call: bool) -> Result<(), GameError> {
+        let proposed = match &self.phase {
+            Phase::SecondBest(turn) => turn.clone(),
+            Phase::Over(_) => return Err(GameError::GameOver),
+            _ => return Err(GameError::NoSecondBestPending),
+        };
+        if colour != self.deciding() {
+            return Err(GameError::NotYourTurn);
+        }
+        if call && !self.can_call_second_best() {
+            return Err(GameError::SecondBestNotAllowed);
+        }
+        if call {
+            self.phase = Phase::Retry(proposed);
+        } else if self.passes + 2 < self.players() {
+            self.passes += 1;
+        } else {
+            self.play_round(Round {
+                proposed,
+                second_turn: None,
+            });
+        }
+        Ok(())
+    }
+
+    // Play another turn after second best was called on the proposed one
+    pub fn retry_turn(&mut self, turn: Turn) -> Result<(), GameError> {
+        let vetoed = match &self.phase {
+            Phase::Retry(turn) => turn.clone(),
+            Phase::Over(_) => return Err(GameError::GameOver),
+            _ => return Err(GameError::NotYourTurn),
+        };
+        if turn.colour() != self.to_move() {
+            return Err(GameError::NotYourTurn);
+        }
+        if !self.is_legal_turn(&turn) || (turn == vetoed && !self.rules.replay_vetoed) {
+            return Err(GameError::IllegalTurn);
+        }
+        self.play_round(Round {
+            proposed: vetoed,
+            second_turn: Some(turn),
+        });
+        Ok(())
+    }
+
+    // The colour that called second best in each round, None where the proposed turn was played
+    pub fn callers(&self) -> &[Option<Colour>] {
+        &self.callers
+    }
+
+    // The number of players that let the proposed turn pass so far
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    // Give up the game, the opponent wins
+    pub fn resign(&mut self, colour: Colour) -> Result<(), GameError> {
+        self.end(colour, EndReason::Resignation)
+    }
+
+    // End the game early, the colour loses for the reason given.
+    // With more than two players the others share the game as a draw.
+    pub fn end(&mut self, colour: Colour, reason: EndReason) -> Result<(), GameError> {
+        if let Phase::Over(_) = self.phase {
+            return Err(GameError::GameOver);
+        }
+        let result = if self.players() == 2 {
+            EndState::Win(colour.opposite())
+        } else {
+            EndState::Draw
+        };
+        self.phase = Phase::Over(Outcome::new(result, reason));
+        Ok(())
+    }
+
+    // Second best can only be called with calls left and if the side to move has another turn to play
+    pub fn can_call_second_best(&self) -> bool {
+        self.second_best_calls_left(self.deciding()) != Some(0)
+            && (self.rules.replay_vetoed || self.rules.possible_turns(&self.board, self.to_move()).len() > 1)
+    }
+
+    // How often a colour may still call second best, unlimited if None
+    pub fn second_best_calls_left(&self, colour: Colour) -> Option<usize> {
+        let used = self.callers.iter().filter(|caller| **caller == Some(colour)).count();
+        self.rules.second_best_calls.map(|calls| calls.saturating_sub(used))
+    }
+
+    fn play_round(&mut self, round: Round) {
+        let caller = round.second_turn.as_ref().map(|_| self.deciding());
+        self.board.do_turn(round.played());
+        self.turns.push(round.played().clone());
+        self.rounds.push(round);
+        self.callers.push(caller);
+        self.positions.push(self.board.clone());
+        self.passes = 0;
+        self.phase = self.check_end().map_or(Phase::Propose, Phase::Over);
+    }
+
+    // The colour that made the turn of the last round
+    fn last_to_move(&self) -> Colour {
+        Colour::ALL[(self.rounds.len() + self.players() - 1) % self.players()]
+    }
+
+    // The game ends on a winning pattern, a threefold repetition or when the side to move is stuck,
+    // in which case the player before it wins
+    fn check_end(&self) -> Option<Outcome> {
+        if let Some(outcome) = self.rules.outcome(&self.board, self.last_to_move()) {
+            return Some(outcome);
+        }
+        // The same side is to move in positions a multiple of the number of players rounds apart
+        let repetitions = self
+            .positions
+            .iter()
+            .rev()
+            .step_by(self.players())
+            .filter(|board| **board == self.board)
+            .count();
+        if repetitions >= 3 {
+            return Some(Outcome::new(EndState::Draw, EndReason::Repetition));
+        }
+        if self.rules.possible_turns(&self.board, self.to_move()).is_empty() {
+            return Some(Outcome::new(EndState::Win(self.last_to_move()), EndReason::NoLegalMove));
         }
+        None
     }
 
     pub fn start_game(&mut self) {
-        match IO::start_game() {
-            Some(Colour::White) => self.gameloop((
-                Box::new(Person::new(Colour::White)),
-                Box::new(Bot::new(Colour::Black, 3)),
-            )),
-            Some(Colour::Black) => self.gameloop((
-                Box::new(Bot::new(Colour::White, 3)),
-                Box::new(Person::new(Colour::Black)),
-            )),
-            None => self.gameloop((
-                Box::new(Person::new(Colour::White)),
-                Box::new(Person::new(Colour::Black)),
-            )),
-        }
-    }
-
-    fn gameloop(&mut self, players: (Box<dyn Player>, Box<dyn Player>)) {
-        let mut turn_counter = 0;
+        // Either a person plays one colour against bots or people play all colours
+        let person = IO::start_game(self.players());
+        let players = Colour::playing(self.players())
+            .iter()
+            .map(|&colour| -> Box<dyn Player> {
+                if person.is_none_or(|person| person == colour) {
+                    Box::new(Person::new(colour))
+                } else {
+                    Box::new(
+                        Bot::new(colour, 3)
+                            .with_rules(self.rules.clone())
+                            .with_pondering(true)
+                            .with_seed(self.bot_seed(colour)),
+                    )
+                }
+            })
+            .collect();
+        self.gameloop(players)
+    }
+
+    // Let the bot play against an external engine, see `protocol` for what the engine has to speak
+    pub fn start_engine_game(&mut self, command: &str, bot_colour: Colour) {
+        let engine = match ExternalEngine::launch(bot_colour.opposite(), command, ENGINE_MOVE_TIME, &self.rules) {
+            Ok(engine) => Box::new(engine),
+            Err(error) => return IO::engine_failed(command, &error),
+        };
+        let bot = Box::new(Bot::new(bot_colour, 3).with_rules(self.rules.clone()).with_seed(self.bot_seed(bot_colour)));
+        self.gameloop(vec![bot, engine])
+    }
+
+    // Wait for another instance to join on the port and play against the person there
+    pub fn host_game(&mut self, port: u16, colour: Colour) {
+        IO::waiting_for_peer(port);
+        match network::host(port, colour, &self.rules) {
+            Ok((connection, _)) => self.network_game(connection, colour),
+            Err(error) => IO::network_failed(&error),
+        }
+    }
+
+    // Join a game hosted by another instance
+    pub fn join_game(&mut self, address: &str) {
+        match network::join(address, &self.rules) {
+            Ok((connection, colour)) => self.network_game(connection, colour),
+            Err(error) => IO::network_failed(&error),
+        }
+    }
+
+    fn network_game(&mut self, connection: Rc<Connection>, colour: Colour) {
+        IO::connected(colour);
+        let local = Box::new(NetworkedPlayer::new(Box::new(Person::new(colour)), connection.clone()));
+        let remote = Box::new(RemotePlayer::new(colour.opposite(), connection));
+        self.gameloop(vec![local, remote])
+    }
+
+    // Play the game with a player for every colour, the players are asked in turn order
+    // and all of them are told about every step of the game
+    fn gameloop(&mut self, mut players: Vec<Box<dyn Player>>) {
+        let index_of = |players: &[Box<dyn Player>], colour: Colour| {
+            players
+                .iter()
+                .position(|player| player.get_colour() == colour)
+                .expect("every colour has a player")
+        };
         IO::print_board(&self.board);
 
         // Loop rounds of the game
         loop {
-            // Determine the player making a turn each round (and the opponent deciding second best)
-            let player = if turn_counter % 2 == 0 { &players.0 } else { &players.1 };
-            let opponent = if turn_counter % 2 == 0 { &players.1 } else { &players.0 };
-
-            // Stop the game if someone has won
-            if let Some(winstate) = self.board.is_won() {
-                IO::end_game(winstate);
+            // Stop the game if it has ended on the board
+            if let Phase::Over(_) = self.phase {
+                self.notify_game_over(&mut players);
+                self.show_end();
+                self.offer_analysis();
                 return;
             }
 
-            // A turn in round is different depending on in placing or moving phase
-            // Bool determins if it is a second turn after second best was declared
-            let try_turn = |b: bool| {
-                if self.board.count_pieces() < 16 { // Placing
-                    let p = player.ask_put_piece(&self.board, b);
-                    Turn::Place(player.get_colour(), p)
-                } else { // Moving
-                    let m = player.ask_move_piece(&self.board, b);
-                    Turn::Move(player.get_colour(), m.0, m.1)
-                }
-            };
+            // Determine the player making a turn this round
+            let mover = index_of(&players, self.to_move());
 
             // Keep asking for turn until a valid one is given
-            let mut turn: Turn;
-            let mut valid_turn: bool;
-            loop {
-                turn = try_turn(false);
-                valid_turn = self.board.is_possible_turn(&turn);
-                if !valid_turn {
-                    IO::invalid_turn();
-                } else {
-                    break;
+            let mut turn = loop {
+                match self.ask_turn(players[mover].as_mut()) {
+                    Some(Decision::Play(turn)) => {
+                        if self.propose_turn(turn.clone()).is_ok() {
+                            break turn;
+                        }
+                        IO::invalid_turn();
+                    }
+                    Some(Decision::Resign) => return self.resign_game(&mut players, mover),
+                    None => return self.forfeit(&mut players, mover),
                 }
-            }
-
+            };
+            players.iter_mut().for_each(|player| player.turn_proposed(self, &turn));
 
             // Output the current board state, might change because of second best so temp board is made to apply move
             let mut temp_board = self.board.clone();
             temp_board.do_turn(&turn);
-            IO::print_board(&temp_board);
-
-            // Ask opponent for second best
-            if opponent.ask_second_best(&self.board, &turn) {
+            IO::print_board_after(&temp_board, &turn);
+
+            // Ask the other players in turn for second best, unless the player has no other turn to play
+            while let (Phase::SecondBest(_), Some(colour)) = (&self.phase, self.waiting_for()) {
+                let opponent = index_of(&players, colour);
+                let call_second_best = self.can_call_second_best() && players[opponent].ask_second_best(self, &turn);
+                if players[opponent].forfeit_reason().is_some() {
+                    return self.forfeit(&mut players, opponent);
+                }
+                let _ = self.decide_second_best(colour, call_second_best);
+                players.iter_mut().for_each(|player| player.second_best_decided(self, colour, call_second_best));
+            }
+            if let Phase::Retry(_) = self.phase {
                 IO::result_second_best(true);
 
                 // Demand valid turn again, but also cannot be equal to turn made in previous step
-                let first_turn = turn.clone();
-                loop {
-                    turn = try_turn(true);
-                    valid_turn = self
-                        .board
-                        .is_possible_turn(&turn)
-                        && turn != first_turn;
-                    if !valid_turn {
-                        IO::invalid_turn();
-                    } else {
-                        break;
+                turn = loop {
+                    match self.ask_turn(players[mover].as_mut()) {
+                        Some(Decision::Play(turn)) => {
+                            if self.retry_turn(turn.clone()).is_ok() {
+                                break turn;
+                            }
+                            IO::invalid_turn();
+                        }
+                        Some(Decision::Resign) => return self.resign_game(&mut players, mover),
+                        None => return self.forfeit(&mut players, mover),
                     }
-                }
+                };
             }
+            players.iter_mut().for_each(|player| player.turn_played(self, &turn));
+            IO::print_board_after(&self.board, &turn);
+        }
+    }
 
-            // Apply the turn to the board
-            self.board.do_turn(&turn);
-            IO::print_board(&self.board);
-            self.turns.push(turn);
-            turn_counter += 1;
+    // Ask a player for its decision, None if it forfeits the game instead
+    fn ask_turn(&self, player: &mut dyn Player) -> Option<Decision> {
+        let decision = player.ask_turn(self);
+        player.forfeit_reason().is_none().then_some(decision)
+    }
+
+    fn notify_game_over(&self, players: &mut [Box<dyn Player>]) {
+        if let Phase::Over(outcome) = &self.phase {
+            players.iter_mut().for_each(|player| player.game_over(self, outcome));
+        }
+    }
+
+    fn resign_game(&mut self, players: &mut [Box<dyn Player>], idx: usize) {
+        let _ = self.resign(players[idx].get_colour());
+        self.notify_game_over(players);
+        self.show_end();
+    }
+
+    // End the game because a player could not continue it
+    fn forfeit(&mut self, players: &mut [Box<dyn Player>], idx: usize) {
+        let player = &players[idx];
+        IO::forfeit(player.get_colour(), &player.forfeit_reason().unwrap_or_default());
+        let _ = self.end(player.get_colour(), player.forfeit_end_reason());
+        self.notify_game_over(players);
+        self.show_end();
+    }
+
+    // Announce the result with the record of the game and the seed to play it again
+    fn show_end(&self) {
+        if let Phase::Over(outcome) = &self.phase {
+            IO::end_game(outcome, &self.board.config);
+            IO::print_record(&notation::record_to_string(&self.rounds), self.seed);
+        }
+    }
+
+    // Let the players go through the finished game with the engine's annotations
+    fn offer_analysis(&self) {
+        if !IO::ask_analysis() {
+            return;
+        }
+        let rounds = analysis::analyse_game(self, analysis::ANALYSIS_DEPTH);
+        let record = format!("# seed {}\n{}", self.seed, analysis::annotated_record(&rounds, self.players()));
+        IO::print_analysis(&record);
+        if let Some(path) = IO::ask_save_path() {
+            if let Err(error) = std::fs::write(&path, record) {
+                IO::save_failed(&path, &error);
+            }
         }
     }
 }
\ No newline at end of file
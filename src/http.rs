@@ -0,0 +1,461 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    bot::{self, Bot},
+    game::*,
+    rules::RuleSet,
+};
+
+// A JSON API on localhost for tools driving games, see `schema` for the JSON of the game types.
+// Every request gets a JSON answer, errors are {"error": <message>} with a matching status.
+// Bodies have to be sent as application/json.
+//
+//   GET  /                               a page to play in the browser, built on the requests below
+//   GET  /rules                          the rules games are created with unless they bring their own
+//   GET  /games                          the games with their players and whether they are over
+//   POST /games                          {"players": [seat, ...], "rules": rules, "seed": seed}, creates a game,
+//                                        rules and the seed of the bots are optional
+//   GET  /games/<id>                     the state of a game
+//   POST /games/<id>/turns               turn, proposes the turn or plays it after second best was called
+//   POST /games/<id>/second-best         {"colour": colour, "call": true|false}
+//   POST /games/<id>/resign              {"colour": colour}
+//   GET  /games/<id>/analysis[?depth=n]  the scores of the turns of the colour to move as the bot sees them
+//
+// A seat is "human" or {"bot": <depth>}, one for every colour in turn order. Depths go up to `MAX_DEPTH`. Bots act on their own
+// after every change, so clients poll the state until the game waits for a human again. A state is
+//   {"id": id, "players": [seat, ...], "waiting_for": colour | null, "legal_turns": [turn, ...],
+//    "can_call_second_best": true|false, "game": game}
+
+pub const DEFAULT_PORT: u16 = 7980;
+
+// Search depth of analyses when the client does not choose one
+const DEFAULT_ANALYSIS_DEPTH: u64 = 3;
+
+// Deeper searches of bots and analyses are refused, they would keep a thread busy for too long
+pub const MAX_DEPTH: u64 = 6;
+
+// The browser page, self-contained so the binary serves it without any files around
+const UI_PAGE: &str = include_str!("ui.html");
+
+// Requests with larger bodies are refused
+const MAX_BODY: usize = 1 << 20;
+
+// Pause after failing to accept a connection before trying again
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Seat {
+    Human,
+    Bot(u64),
+}
+
+struct HttpGame {
+    game: Game,
+    players: Vec<Seat>,
+}
+
+impl HttpGame {
+    fn seat(&self, colour: Colour) -> Seat {
+        let idx = Colour::ALL.iter().position(|c| *c == colour).unwrap_or(0);
+        self.players[idx]
+    }
+
+    fn state(&self, id: usize) -> Value {
+        let can_call = matches!(self.game.phase, Phase::SecondBest(_)) && self.game.can_call_second_best();
+        json!({
+            "id": id,
+            "players": self.players,
+            "waiting_for": self.game.waiting_for(),
+            "legal_turns": self.game.legal_turns(),
+            "can_call_second_best": can_call,
+            "game": self.game,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Games {
+    games: BTreeMap<usize, HttpGame>,
+    next_game: usize,
+}
+
+type Shared = Arc<Mutex<Games>>;
+
+#[derive(Deserialize)]
+struct CreateRequest {
+    players: Vec<Seat>,
+    rules: Option<RuleSet>,
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SecondBestRequest {
+    colour: Colour,
+    call: bool,
+}
+
+#[derive(Deserialize)]
+struct ResignRequest {
+    colour: Colour,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn ok(body: Value) -> Self {
+        Self::json(200, body)
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into() }))
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+// Illegal turns are well formed but break the rules, the other errors are about the state of the game
+fn game_error(error: GameError) -> Response {
+    let status = match error {
+        GameError::IllegalTurn | GameError::SecondBestNotAllowed => 422,
+        GameError::GameOver | GameError::NotYourTurn | GameError::NoSecondBestPending => 409,
+    };
+    Response::error(status, error.to_string())
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(request: &'a Request) -> Result<T, Response> {
+    let media_type = request.content_type.split(';').next().unwrap_or("").trim();
+    if !media_type.eq_ignore_ascii_case("application/json") {
+        return Err(Response::error(415, "expected a body of type application/json"));
+    }
+    serde_json::from_slice(&request.body).map_err(|error| Response::error(400, format!("invalid body: {}", error)))
+}
+
+fn parse_id(id: &str) -> Result<usize, Response> {
+    id.parse::<usize>().map_err(|_| Response::error(404, format!("no game {}", id)))
+}
+
+fn route(games: &Shared, rules: &RuleSet, request: &Request) -> Response {
+    let segments: Vec<&str> = request.path.split('/').filter(|segment| !segment.is_empty()).collect();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", []) => Ok(Response {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: UI_PAGE.to_owned(),
+        }),
+        ("GET", ["rules"]) => Ok(Response::ok(json!(rules))),
+        ("GET", ["games"]) => Ok(list(games)),
+        ("POST", ["games"]) => parse_body(request).and_then(|body| create(games, rules, body)),
+        ("GET", ["games", id]) => parse_id(id).and_then(|id| state(games, id)),
+        ("POST", ["games", id, "turns"]) => parse_id(id).and_then(|id| turn(games, id, parse_body::<Turn>(request)?)),
+        ("POST", ["games", id, "second-best"]) => parse_id(id).and_then(|id| second_best(games, id, parse_body(request)?)),
+        ("POST", ["games", id, "resign"]) => parse_id(id).and_then(|id| resign(games, id, parse_body(request)?)),
+        ("GET", ["games", id, "analysis"]) => parse_id(id).and_then(|id| analysis(games, id, &request.query)),
+        (_, [] | ["rules"] | ["games"] | ["games", _] | ["games", _, "turns" | "second-best" | "resign" | "analysis"]) => {
+            Err(Response::error(405, format!("{} is not allowed on {}", request.method, request.path)))
+        }
+        _ => Err(Response::error(404, format!("nothing at {}", request.path))),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+fn list(games: &Shared) -> Response {
+    let games = games.lock().unwrap();
+    let list: Vec<Value> = games
+        .games
+        .iter()
+        .map(|(id, game)| json!({ "id": id, "players": game.players, "over": matches!(game.game.phase, Phase::Over(_)) }))
+        .collect();
+    Response::ok(Value::Array(list))
+}
+
+fn create(games: &Shared, rules: &RuleSet, request: CreateRequest) -> Result<Response, Response> {
+    let rules = request.rules.unwrap_or_else(|| rules.clone());
+    if !rules.board.is_valid() {
+        return Err(Response::error(422, "invalid board dimensions"));
+    }
+    if request.players.len() != rules.board.players {
+        return Err(Response::error(422, format!("expected {} players", rules.board.players)));
+    }
+    if request.players.iter().any(|seat| matches!(seat, Seat::Bot(depth) if *depth > MAX_DEPTH)) {
+        return Err(Response::error(422, format!("bots search to a depth of at most {}", MAX_DEPTH)));
+    }
+    let mut shared = games.lock().unwrap();
+    let id = shared.next_game;
+    shared.next_game += 1;
+    let game = HttpGame {
+        game: Game::with_rules(rules).with_seed(request.seed.unwrap_or_else(bot::next_seed)),
+        players: request.players,
+    };
+    let state = game.state(id);
+    shared.games.insert(id, game);
+    drop(shared);
+    start_bots(games, id);
+    Ok(Response::json(201, state))
+}
+
+fn state(games: &Shared, id: usize) -> Result<Response, Response> {
+    let games = games.lock().unwrap();
+    let game = games.games.get(&id).ok_or_else(|| Response::error(404, format!("no game {}", id)))?;
+    Ok(Response::ok(game.state(id)))
+}
+
+// Apply an action of a human to a game and let the bots answer it
+fn act(games: &Shared, id: usize, colour: Colour, action: impl FnOnce(&mut Game) -> Result<(), GameError>) -> Result<Response, Response> {
+    let mut shared = games.lock().unwrap();
+    let game = shared.games.get_mut(&id).ok_or_else(|| Response::error(404, format!("no game {}", id)))?;
+    if !Colour::playing(game.players.len()).contains(&colour) {
+        return Err(Response::error(422, format!("{:?} does not play in this game", colour)));
+    }
+    if game.seat(colour) != Seat::Human {
+        return Err(Response::error(409, format!("{:?} is played by a bot", colour)));
+    }
+    action(&mut game.game).map_err(game_error)?;
+    let state = game.state(id);
+    drop(shared);
+    start_bots(games, id);
+    Ok(Response::ok(state))
+}
+
+fn turn(games: &Shared, id: usize, turn: Turn) -> Result<Response, Response> {
+    act(games, id, turn.colour(), |game| match game.phase {
+        Phase::Retry(_) => game.retry_turn(turn),
+        _ => game.propose_turn(turn),
+    })
+}
+
+fn second_best(games: &Shared, id: usize, request: SecondBestRequest) -> Result<Response, Response> {
+    act(games, id, request.colour, |game| game.decide_second_best(request.colour, request.call))
+}
+
+fn resign(games: &Shared, id: usize, request: ResignRequest) -> Result<Response, Response> {
+    act(games, id, request.colour, |game| game.resign(request.colour))
+}
+
+// The turns of the colour to move scored by a bot, best first, and whether the bot would call
+// second best on a proposed turn
+fn analysis(games: &Shared, id: usize, query: &str) -> Result<Response, Response> {
+    let depth = match query.split('&').find_map(|pair| pair.strip_prefix("depth=")) {
+        Some(depth) => depth.parse::<u64>().map_err(|_| Response::error(400, format!("invalid depth {}", depth)))?,
+        None => DEFAULT_ANALYSIS_DEPTH,
+    };
+    if depth > MAX_DEPTH {
+        return Err(Response::error(422, format!("analyses search to a depth of at most {}", MAX_DEPTH)));
+    }
+    // Search without holding the lock so other games keep going
+    let (board, rules, colour, phase) = {
+        let games = games.lock().unwrap();
+        let game = &games.games.get(&id).ok_or_else(|| Response::error(404, format!("no game {}", id)))?.game;
+        (game.board.clone(), game.rules.clone(), game.to_move(), game.phase.clone())
+    };
+    let bot = Bot::new(colour, depth).with_rules(rules);
+    let mut scores = bot.score_turns(&board, colour);
+    scores.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let turns: Vec<Value> = scores.iter().map(|(turn, score)| json!({ "turn": turn, "score": score })).collect();
+    let call_second_best = match &phase {
+        Phase::SecondBest(turn) => Some(bot.would_call_second_best(&board, turn)),
+        _ => None,
+    };
+    Ok(Response::ok(json!({
+        "colour": colour,
+        "depth": depth,
+        "turns": turns,
+        "call_second_best": call_second_best,
+    })))
+}
+
+fn start_bots(games: &Shared, id: usize) {
+    let games = games.clone();
+    thread::spawn(move || run_bots(&games, id));
+}
+
+// Let the bots act in a game for as long as it waits for them
+fn run_bots(games: &Mutex<Games>, id: usize) {
+    loop {
+        // Search without holding the lock so other games keep going
+        let job = {
+            let games = games.lock().unwrap();
+            let Some(game) = games.games.get(&id) else {
+                return;
+            };
+            let Some(colour) = game.game.waiting_for() else {
+                return;
+            };
+            match game.seat(colour) {
+                Seat::Bot(depth) => (
+                    colour,
+                    Bot::new(colour, depth)
+                        .with_rules(game.game.rules.clone())
+                        .with_seed(game.game.bot_seed(colour)),
+                    game.game.board.clone(),
+                    game.game.phase.clone(),
+                    game.game.rounds.len(),
+                ),
+                Seat::Human => return,
+            }
+        };
+        let (colour, bot, board, phase, rounds) = job;
+        let action = match &phase {
+            Phase::Propose => BotAction::Turn(bot.choose_turn(&board, None)),
+            Phase::SecondBest(turn) => BotAction::SecondBest(bot.would_call_second_best(&board, turn)),
+            Phase::Retry(vetoed) => BotAction::Turn(bot.choose_turn(&board, Some(vetoed))),
+            Phase::Over(_) => return,
+        };
+
+        let mut games = games.lock().unwrap();
+        let Some(game) = games.games.get_mut(&id) else {
+            return;
+        };
+        // Another thread may have acted for the bot in the meantime
+        if game.game.phase != phase || game.game.rounds.len() != rounds {
+            continue;
+        }
+        let game = &mut game.game;
+        let result = match action {
+            BotAction::Turn(turn) if matches!(phase, Phase::Retry(_)) => game.retry_turn(turn),
+            BotAction::Turn(turn) => game.propose_turn(turn),
+            BotAction::SecondBest(call) => game.decide_second_best(colour, call && game.can_call_second_best()),
+        };
+        if result.is_err() {
+            return;
+        }
+    }
+}
+
+enum BotAction {
+    Turn(Turn),
+    SecondBest(bool),
+}
+
+// Read a request, the body only as far as the content length says
+fn read_request(stream: &TcpStream) -> Result<Request, Response> {
+    let bad_request = |_| Response::error(400, "invalid request");
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(bad_request)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "invalid request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        query: query.to_owned(),
+        content_type: String::new(),
+        body: Vec::new(),
+    };
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(bad_request)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().map_err(|_| Response::error(400, "invalid content length"))?;
+            } else if name.eq_ignore_ascii_case("content-type") {
+                request.content_type = value.trim().to_owned();
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Response::error(413, "body too large"));
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body).map_err(bad_request)?;
+    Ok(request)
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason_phrase(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+// One request per connection
+fn handle_client(games: Shared, rules: RuleSet, stream: TcpStream) -> io::Result<()> {
+    let response = match read_request(&stream) {
+        Ok(request) => route(&games, &rules, &request),
+        Err(response) => response,
+    };
+    write_response(&stream, &response)
+}
+
+// Answer requests on a listener until the server is killed, games are created with the rules unless a request brings its own
+pub fn serve(listener: TcpListener, rules: RuleSet) -> io::Result<()> {
+    let games = Shared::default();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("could not accept a connection: {}", error);
+                // Errors like running out of file descriptors last a while
+                thread::sleep(ACCEPT_RETRY);
+                continue;
+            }
+        };
+        let games = games.clone();
+        let rules = rules.clone();
+        thread::spawn(move || handle_client(games, rules, stream));
+    }
+    Ok(())
+}
+
+// Listen on the port of localhost only, the API is for tools on the same machine
+pub fn run(port: u16, rules: RuleSet) -> io::Result<()> {
+    serve(TcpListener::bind(("127.0.0.1", port))?, rules)
+}
@@ -1,22 +1,37 @@
+use crate::bot::Bot;
 use crate::game::*;
+use crate::notation;
+use crate::render::{self, Highlight};
+use crate::rules::RuleSet;
 use std::io;
 
+// Search depth used by the engine when giving hints to a person
+const HINT_DEPTH: u64 = 3;
+
 pub struct IO();
 
 impl IO {
-    pub fn start_game() -> Option<Colour> {
+    // Ask which colour the person plays against bots, None if people play all colours
+    pub fn start_game(players: usize) -> Option<Colour> {
         println!("Welcome to 'Second Best', follow the instructions to start a game. White always starts.");
         loop {
-            println!("Play against bot as White or Black (w/b) or against another player (p)?");
+            if players == 2 {
+                println!("Play against bot as White or Black (w/b) or against another player (p)?");
+            } else {
+                println!("Play against bots as White, Black or Red (w/b/r) or against other players (p)?");
+            }
             let mut input = String::new();
             match io::stdin().read_line(&mut input) {
                 Ok(_) => match input.trim() {
-                    "w" => return Some(Colour::White),
-                    "b" => return Some(Colour::Black),
                     "p" => return None,
-                    _ => {
-                        println!("Invalid input");
-                    }
+                    input => match input.chars().next().and_then(notation::parse_colour) {
+                        Some(colour) if input.len() == 1 && Colour::playing(players).contains(&colour) => {
+                            return Some(colour)
+                        }
+                        _ => {
+                            println!("Invalid input");
+                        }
+                    },
                 },
                 Err(error) => println!("error: {error}"),
             }
@@ -42,15 +57,54 @@ impl IO {
         println!("Computer has {}.", move_string);
     }
 
-    pub fn ask_second_best() -> bool {
+    pub fn turn_string(turn: &Turn) -> String {
+        match *turn {
+            Turn::Place(_, place) => format!("place a piece at {}", place + 1),
+            Turn::Move(_, from_place, to_place) => {
+                format!("move a piece from {} to {}", from_place + 1, to_place + 1)
+            }
+        }
+    }
+
+    // Let the engine recommend a turn for a person, playing by the rules of the game
+    pub fn hint_turn(rules: &RuleSet, board: &Board, colour: Colour, second_best: bool) {
+        let bot = Bot::new(colour, HINT_DEPTH).with_rules(rules.clone());
+        let (turns, score) = bot.recommend(board, colour, second_best);
+        let turn_strings: Vec<String> = turns.iter().map(Self::turn_string).collect();
+        println!(
+            "Hint: {} (evaluation {:+.1})",
+            turn_strings.join(" or "),
+            score
+        );
+    }
+
+    // Let the engine judge whether a person playing a colour should call second best on a proposed turn
+    pub fn hint_second_best(rules: &RuleSet, board: &Board, colour: Colour, turn: &Turn) {
+        let bot = Bot::new(colour, HINT_DEPTH).with_rules(rules.clone());
+        let score = bot.evaluate_turn(board, turn);
+        if bot.would_call_second_best(board, turn) {
+            println!(
+                "Hint: call second best, this is your opponent's best turn (evaluation {:+.1} for them).",
+                score
+            );
+        } else {
+            println!(
+                "Hint: let it pass, this is not your opponent's best turn (evaluation {:+.1} for them).",
+                score
+            );
+        }
+    }
+
+    pub fn ask_second_best(colour: Colour, rules: &RuleSet, board: &Board, turn: &Turn) -> bool {
         loop {
-            println!("Second best? (y/n):");
+            println!("{:?}, second best? (y/n/hint):", colour);
 
             let mut input = String::new();
             match io::stdin().read_line(&mut input) {
                 Ok(_) => match input.trim() {
                     "y" => return true,
                     "n" => return false,
+                    "hint" => Self::hint_second_best(rules, board, colour, turn),
                     _ => {
                         println!("Invalid input");
                     }
@@ -66,32 +120,36 @@ impl IO {
         }
     }
 
-    pub fn ask_move_piece(colour: Colour) -> (usize, usize) {
+    // Ask for a move, None if the person resigns
+    pub fn ask_move_piece(colour: Colour, rules: &RuleSet, board: &Board, second_best: bool) -> Option<(usize, usize)> {
+        let stacks = board.places.len();
         loop {
-            println!("{:?}, move a piece. (1-8) (1-8): ", colour);
+            println!("{:?}, move a piece. (1-{}) (1-{}), hint or resign: ", colour, stacks, stacks);
 
             let mut input = String::new();
             match io::stdin().read_line(&mut input) {
                 Ok(_) => {
                     input = input.trim().to_owned();
-                    match (input.chars().nth(0), input.chars().nth(2)) {
-                        (Some(a), Some(b)) => {
-                            match (
-                                a.to_string().parse::<usize>(),
-                                b.to_string().parse::<usize>(),
-                            ) {
-                                (Ok(n), Ok(m)) => {
-                                    if n >= 1 && n <= 8 && m >= 1 && m <= 8 {
-                                        return (n - 1, m - 1);
-                                    } else {
-                                        println!("Invalid input");
-                                    }
-                                }
-                                _ => {
-                                    println!("Invalid input");
-                                }
+                    if input == "hint" {
+                        Self::hint_turn(rules, board, colour, second_best);
+                        continue;
+                    }
+                    if input == "resign" {
+                        return None;
+                    }
+                    let numbers: Vec<&str> = input
+                        .split(|c: char| !c.is_ascii_digit())
+                        .filter(|number| !number.is_empty())
+                        .collect();
+                    match numbers.as_slice() {
+                        [a, b] => match (a.parse::<usize>(), b.parse::<usize>()) {
+                            (Ok(n), Ok(m)) if (1..=stacks).contains(&n) && (1..=stacks).contains(&m) => {
+                                return Some((n - 1, m - 1));
                             }
-                        }
+                            _ => {
+                                println!("Invalid input");
+                            }
+                        },
                         _ => {
                             println!("Invalid input");
                         }
@@ -102,77 +160,146 @@ impl IO {
         }
     }
 
-    pub fn ask_put_piece(colour: Colour) -> usize {
+    // Ask where to place a piece, None if the person resigns
+    pub fn ask_put_piece(colour: Colour, rules: &RuleSet, board: &Board, second_best: bool) -> Option<usize> {
+        let stacks = board.places.len();
         loop {
-            println!("{:?}, place a piece. (1-8): ", colour);
+            println!("{:?}, place a piece. (1-{}), hint or resign: ", colour, stacks);
 
             let mut input = String::new();
             match io::stdin().read_line(&mut input) {
-                Ok(_) => match input.trim().parse::<usize>() {
-                    Ok(n) => {
-                        if n >= 1 && n <= 8 {
-                            return n - 1;
-                        } else {
+                Ok(_) => match input.trim() {
+                    "hint" => Self::hint_turn(rules, board, colour, second_best),
+                    "resign" => return None,
+                    input => match input.parse::<usize>() {
+                        Ok(n) if (1..=stacks).contains(&n) => {
+                            return Some(n - 1);
+                        }
+                        _ => {
                             println!("Invalid input");
                         }
-                    }
-                    Err(_) => {
-                        println!("Invalid input");
-                    }
+                    },
                 },
                 Err(error) => println!("error: {error}"),
             }
         }
     }
 
-    pub fn end_game(endstate: EndState) {
-        match endstate {
+    pub fn forfeit(colour: Colour, reason: &str) {
+        println!("{:?} forfeits the game: {}", colour, reason);
+    }
+
+    pub fn engine_failed(command: &str, error: &io::Error) {
+        println!("Could not start engine {}: {}", command, error);
+    }
+
+    pub fn waiting_for_peer(port: u16) {
+        println!("Waiting for another player to join on port {}, it is open to other machines on the network...", port);
+    }
+
+    pub fn connected(colour: Colour) {
+        println!("Connected, you play {:?}.", colour);
+    }
+
+    pub fn network_failed(error: &io::Error) {
+        println!("Network game failed: {}", error);
+    }
+
+    pub fn end_game(outcome: &Outcome, config: &BoardConfig) {
+        match outcome.result {
             EndState::Win(colour) => {
-                println!("{:?} has won the game!", colour);
+                println!("{:?} has won the game {}!", colour, Self::reason_string(outcome, config));
             }
             EndState::Draw => {
-                println!("The game is a draw.");
+                println!("The game is a draw {}.", Self::reason_string(outcome, config));
             }
         }
     }
 
-    pub fn piece_string(piece: &Piece) -> String {
-        match piece {
-            Piece::Blank => " ".to_owned(),
-            Piece::Piece(Colour::White) => "□".to_owned(),
-            Piece::Piece(Colour::Black) => "■".to_owned(),
+    pub fn print_record(record: &str, seed: u64) {
+        println!("Record: {}", record);
+        println!("Seed: {} (play again with --seed {})", seed, seed);
+    }
+
+    // Why the game ended, the length of a winning line is the one the board is played with
+    pub fn reason_string(outcome: &Outcome, config: &BoardConfig) -> String {
+        const NUMBERS: [&str; 11] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+        let line = NUMBERS.get(config.line).map_or(config.line.to_string(), |number| number.to_string());
+        let stacks = outcome
+            .stacks
+            .iter()
+            .map(|i| (i + 1).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match outcome.reason {
+            EndReason::Stack => format!("with a full stack on {}", stacks),
+            EndReason::RingLine => format!("with {} in a row on {}", line, stacks),
+            EndReason::Simultaneous => format!("as both sides completed a pattern on {}", stacks),
+            EndReason::Repetition => "by threefold repetition".to_owned(),
+            EndReason::Resignation => "by resignation".to_owned(),
+            EndReason::Timeout => "on time".to_owned(),
+            EndReason::NoLegalMove => "as the other side has no turn left".to_owned(),
+            EndReason::Forfeit => "by forfeit".to_owned(),
+        }
+    }
+
+    pub fn ask_analysis() -> bool {
+        loop {
+            println!("Analyse the game? (y/n):");
+
+            let mut input = String::new();
+            match io::stdin().read_line(&mut input) {
+                Ok(_) => match input.trim() {
+                    "y" => return true,
+                    "n" => return false,
+                    _ => {
+                        println!("Invalid input");
+                    }
+                },
+                Err(error) => println!("error: {error}"),
+            }
+        }
+    }
+
+    pub fn print_analysis(record: &str) {
+        print!("{}", record);
+    }
+
+    pub fn ask_save_path() -> Option<String> {
+        println!("Save the analysis to a file? (path, or empty to skip):");
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(_) => match input.trim() {
+                "" => None,
+                path => Some(path.to_owned()),
+            },
+            Err(error) => {
+                println!("error: {error}");
+                None
+            }
         }
     }
 
+    pub fn save_failed(path: &str, error: &io::Error) {
+        println!("Could not save to {}: {}", path, error);
+    }
+
+    pub fn piece_string(piece: &Piece) -> String {
+        render::style().renderer().piece(piece)
+    }
+
     pub fn place_string(place: &Place) -> String {
-        format!(
-            "{}{}{}",
-            Self::piece_string(&place.0[0]),
-            Self::piece_string(&place.0[1]),
-            Self::piece_string(&place.0[2])
-        )
+        place.0.iter().map(Self::piece_string).collect()
     }
 
     pub fn print_board(board: &Board) {
-        println!(
-            "4    [{}] [{}]    5",
-            Self::place_string(&board.0[3]),
-            Self::place_string(&board.0[4])
-        );
-        println!(
-            "3   [{}]   [{}]   6",
-            Self::place_string(&board.0[2]),
-            Self::place_string(&board.0[5])
-        );
-        println!(
-            "2   [{}]   [{}]   7",
-            Self::place_string(&board.0[1]),
-            Self::place_string(&board.0[6])
-        );
-        println!(
-            "1    [{}] [{}]    8",
-            Self::place_string(&board.0[0]),
-            Self::place_string(&board.0[7])
-        );
+        let highlight = Highlight::for_board(board, None);
+        print!("{}", render::style().renderer().board(board, &highlight));
+    }
+
+    // Print the board with the stacks of the turn just made highlighted
+    pub fn print_board_after(board: &Board, turn: &Turn) {
+        let highlight = Highlight::for_board(board, Some(turn));
+        print!("{}", render::style().renderer().board(board, &highlight));
     }
 }
@@ -0,0 +1,148 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    bot::{self, Bot, Stream},
+    eval::{self, Weights, FEATURES},
+    game::*,
+    rules::RuleSet,
+};
+
+// Games still going after this many rounds are counted as draws
+const MAX_ROUNDS: usize = 200;
+
+// How the bot learns its evaluation weights by playing against itself
+#[derive(Debug, Clone)]
+pub struct Training {
+    pub games: usize,
+    // Search depth of the bots playing the games
+    pub depth: u64,
+    // Step size of the weight updates
+    pub rate: f64,
+    // How much of an error is passed back to earlier positions, 0 only updates the position before
+    pub lambda: f64,
+    // Chance of playing a random turn instead of the best one, so the games differ
+    pub exploration: f64,
+    pub rules: RuleSet,
+    // The seeds of the games are taken from this one
+    pub seed: u64,
+}
+
+impl Default for Training {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            depth: 1,
+            rate: 0.5,
+            lambda: 0.7,
+            exploration: 0.1,
+            rules: RuleSet::default(),
+            seed: bot::next_seed(),
+        }
+    }
+}
+
+// Learn weights with TD(λ): after every game of self play the prediction of each position is moved
+// towards the prediction of the position after it, and the last one towards the result of the game.
+// The progress callback gets the number of games played and the weights so far after every game
+pub fn train(
+    training: &Training,
+    mut weights: Weights,
+    mut progress: impl FnMut(usize, &Weights),
+) -> Result<Weights, GameError> {
+    for game in 1..=training.games {
+        let seed = bot::sub_seed(training.seed, Stream::TrainingGame, game as u64);
+        let (positions, result) = self_play(training, &weights, seed)?;
+        td_update(&mut weights, &positions, result, training.rate, training.lambda);
+        progress(game, &weights);
+    }
+    Ok(weights)
+}
+
+// Play a game of the bot against itself, returning the positions after every round that did not
+// end the game and the result for white: 1 for a win, 0 for a loss and a half for a draw.
+// The seed decides the random turns and the ties between turns, so it makes the same game again.
+// The bots and the random turns only play legal turns, a game that fails has found a bug
+pub fn self_play(training: &Training, weights: &Weights, seed: u64) -> Result<(Vec<Board>, f64), GameError> {
+    let mut rng = StdRng::seed_from_u64(bot::sub_seed(seed, Stream::Exploration, 0));
+    let mut game = Game::with_rules(training.rules.clone()).with_seed(seed);
+    let bot = |colour: Colour| {
+        Bot::new(colour, training.depth)
+            .with_rules(training.rules.clone())
+            .with_weights(*weights)
+            .with_threads(1)
+            .with_seed(game.bot_seed(colour))
+    };
+    let bots = [bot(Colour::White), bot(Colour::Black)];
+    let bot_for = |colour: Colour| &bots[if colour == Colour::White { 0 } else { 1 }];
+    let mut positions = Vec::new();
+
+    while game.rounds.len() < MAX_ROUNDS {
+        let rounds = game.rounds.len();
+        let explore = rng.gen_bool(training.exploration);
+        let result = match game.phase.clone() {
+            Phase::Propose => {
+                let turn = match game.legal_turns().choose(&mut rng) {
+                    Some(turn) if explore => turn.clone(),
+                    _ => bot_for(game.to_move()).choose_turn(&game.board, None),
+                };
+                game.propose_turn(turn)
+            }
+            Phase::SecondBest(turn) => {
+                let Some(colour) = game.waiting_for() else {
+                    break;
+                };
+                let call = game.can_call_second_best() && bot_for(colour).would_call_second_best(&game.board, &turn);
+                game.decide_second_best(colour, call)
+            }
+            Phase::Retry(vetoed) => {
+                let turn = match game.legal_turns().choose(&mut rng) {
+                    Some(turn) if explore => turn.clone(),
+                    _ => bot_for(game.to_move()).choose_turn(&game.board, Some(&vetoed)),
+                };
+                game.retry_turn(turn)
+            }
+            Phase::Over(outcome) => {
+                let result = match outcome.result {
+                    EndState::Win(Colour::White) => 1.0,
+                    EndState::Win(_) => 0.0,
+                    EndState::Draw => 0.5,
+                };
+                return Ok((positions, result));
+            }
+        };
+        result?;
+        if game.rounds.len() > rounds && !matches!(game.phase, Phase::Over(_)) {
+            positions.push(game.board.clone());
+        }
+    }
+    Ok((positions, 0.5))
+}
+
+// The chance of white winning from a position, as predicted by the weights
+pub fn predict(weights: &Weights, board: &Board) -> f64 {
+    eval::win_probability(eval::evaluate(weights, board, Colour::White))
+}
+
+// Move the weights so the prediction of every position gets closer to the one after it, with the
+// changes of earlier positions fading by lambda for every round between them
+pub fn td_update(weights: &mut Weights, positions: &[Board], result: f64, rate: f64, lambda: f64) {
+    let predictions: Vec<f64> = positions.iter().map(|board| predict(weights, board)).collect();
+    let mut trace = [0.0; FEATURES];
+    let mut change = [0.0; FEATURES];
+    for (t, board) in positions.iter().enumerate() {
+        let prediction = predictions[t];
+        let features = eval::features(board, Colour::White);
+        // The derivative of the prediction by each weight
+        let slope = prediction * (1.0 - prediction) / eval::PROBABILITY_SCALE;
+        for i in 0..FEATURES {
+            trace[i] = lambda * trace[i] + slope * features[i];
+        }
+        let error = predictions.get(t + 1).copied().unwrap_or(result) - prediction;
+        for i in 0..FEATURES {
+            change[i] += rate * error * trace[i];
+        }
+    }
+    for (weight, change) in weights.0.iter_mut().zip(change) {
+        *weight += change;
+    }
+}
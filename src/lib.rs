@@ -1,4 +1,22 @@
+pub mod analysis;
 pub mod bot;
+pub mod eval;
+pub mod external;
 pub mod game;
+#[cfg(feature = "serde")]
+pub mod http;
 pub mod io;
+pub mod learn;
+pub mod network;
+pub mod notation;
+pub mod perft;
 pub mod player;
+pub mod protocol;
+pub mod render;
+pub mod rules;
+#[cfg(feature = "serde")]
+pub mod schema;
+pub mod search;
+pub mod server;
+pub mod tui;
+pub mod tune;
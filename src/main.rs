@@ -1,3 +1,229 @@
+use second_best::{
+    bot,
+    eval::Weights,
+    game::{Colour, Game},
+    learn,
+    notation,
+    render::{self, Highlight, Style},
+    rules::RuleSet,
+    tune,
+};
+
 fn main() {
-    second_best::game::Game::new().start_game();
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // Options for every mode come before the mode, in any order
+    let mut seed = None;
+    let mut rules = RuleSet::default();
+    loop {
+        match args.get(1).map(String::as_str) {
+            // The board style
+            Some("--render") => match args.get(2).and_then(|style| Style::parse(style)) {
+                Some(style) => render::set_style(style),
+                None => return eprintln!("usage: second_best --render <unicode|ascii|ansi> [mode]"),
+            },
+            // Threads the bots search with, e.g. --threads 4 or --threads all
+            Some("--threads") => match args.get(2).and_then(|threads| bot::parse_threads(threads)) {
+                Some(threads) => bot::set_threads(threads),
+                None => return eprintln!("usage: second_best --threads <n|all> [mode]"),
+            },
+            // Evaluation weights the bots use, e.g. as learned by --train
+            Some("--weights") => match args.get(2).map(|path| Weights::load(path)) {
+                Some(Ok(weights)) => bot::set_weights(weights),
+                Some(Err(error)) => return eprintln!("{}", error),
+                None => return eprintln!("usage: second_best --weights <weights file> [mode]"),
+            },
+            // Seed of the randomness of games and bots, to play the same way again on a single thread
+            Some("--seed") => match args.get(2).map(|seed| seed.parse::<u64>()) {
+                Some(Ok(value)) => {
+                    bot::set_seed(value);
+                    seed = Some(value);
+                }
+                _ => return eprintln!("usage: second_best --seed <number> [mode]"),
+            },
+            // Rules and board dimensions, e.g. --rule stacks 10 --rule line 5
+            Some("--rule") => {
+                let (Some(name), Some(value)) = (args.get(2), args.get(3)) else {
+                    return eprintln!("usage: second_best --rule <name> <value> [mode]");
+                };
+                if let Err(error) = rules.set(name, value) {
+                    return eprintln!("{}", error);
+                }
+                args.remove(1);
+            }
+            _ => break,
+        }
+        args.drain(1..3);
+    }
+    if !rules.board.is_valid() {
+        return eprintln!("invalid board dimensions");
+    }
+    let game = || Game::with_rules(rules.clone()).with_seed(seed.unwrap_or_else(bot::next_seed));
+
+    match args.get(1).map(String::as_str) {
+        Some("--engine") if rules.board.players != 2 => eprintln!("the engine protocol is for two players"),
+        Some("--engine") => second_best::protocol::run(rules),
+        Some("--tui") => {
+            if let Err(error) = second_best::tui::Tui::run(game()) {
+                eprintln!("terminal error: {}", error);
+            }
+        }
+        Some("--versus" | "--host" | "--join") if rules.board.players != 2 => {
+            eprintln!("engine and network games are for two players")
+        }
+        Some("--versus") => match (args.get(2), args.get(3).map(String::as_str)) {
+            (Some(command), None | Some("w")) => game().start_engine_game(command, Colour::White),
+            (Some(command), Some("b")) => game().start_engine_game(command, Colour::Black),
+            _ => eprintln!("usage: second_best --versus <engine command> [w|b]"),
+        },
+        Some("--host") => match (args.get(2).map(|port| port.parse::<u16>()), args.get(3).map(String::as_str)) {
+            (Some(Ok(port)), None | Some("w")) => game().host_game(port, Colour::White),
+            (Some(Ok(port)), Some("b")) => game().host_game(port, Colour::Black),
+            _ => eprintln!("usage: second_best --host <port> [w|b], the port is open to other machines on the network"),
+        },
+        Some("--join") => match args.get(2) {
+            Some(address) => game().join_game(address),
+            None => eprintln!("usage: second_best --join <host:port>"),
+        },
+        Some("--svg") => match (args.get(2), args.get(3)) {
+            (Some(record), Some(output)) => export_game_svg(record, output, &rules),
+            _ => eprintln!("usage: second_best --svg <record file> <output.svg>"),
+        },
+        Some("--svg-board") => match (args.get(2).and_then(|board| notation::parse_board(board, rules.board)), args.get(3)) {
+            (Some(board), Some(output)) => write_file(output, &render::svg_board(&board, &Highlight::for_board(&board, None))),
+            _ => eprintln!("usage: second_best --svg-board <board> <output.svg>"),
+        },
+        Some("--train") => match (args.get(2).map(|games| games.parse::<usize>()), args.get(3), args.get(4).map(|depth| depth.parse::<u64>())) {
+            (Some(Ok(games)), Some(output), None) if rules.board.players == 2 => train(games, output, None, &rules, seed),
+            (Some(Ok(games)), Some(output), Some(Ok(depth))) if rules.board.players == 2 => train(games, output, Some(depth), &rules, seed),
+            _ => eprintln!("usage: second_best --train <games> <weights file> [depth], for two players"),
+        },
+        Some("--tune") => match (args.get(2), args.get(3)) {
+            (Some(collection), Some(output)) => tune(collection, output, &rules),
+            _ => eprintln!("usage: second_best --tune <game collection> <weights file>"),
+        },
+        Some("--http") => http(args.get(2).map(String::as_str), rules),
+        None => game().start_game(),
+        Some(_) => eprintln!("{}", USAGE),
+    }
+}
+
+const USAGE: &str = "usage: second_best [options] [mode]
+options, in any order:
+  --render <unicode|ascii|ansi>
+  --threads <n|all>                more than one thread does not play the same again with a seed
+  --weights <weights file>
+  --seed <number>
+  --rule <name> <value>            may be given more than once
+modes, a game in the terminal without one:
+  --tui
+  --engine
+  --versus <engine command> [w|b]
+  --host <port> [w|b]
+  --join <host:port>
+  --svg <record file> <output.svg>
+  --svg-board <board> <output.svg>
+  --train <games> <weights file> [depth]
+  --tune <game collection> <weights file>
+  --http [port]";
+
+fn export_game_svg(record: &str, output: &str, rules: &RuleSet) {
+    match std::fs::read_to_string(record).map(|record| notation::parse_record(&record, rules.board.players)) {
+        Ok(Some(rounds)) => match Game::from_rounds_with_rules(&rounds, rules.clone()) {
+            Ok(_) => write_file(output, &render::svg_game(&rounds, rules.board)),
+            Err(error) => eprintln!("{} is not a valid game: {}", record, error),
+        },
+        Ok(None) => eprintln!("{} is not a valid record", record),
+        Err(error) => eprintln!("could not read {}: {}", record, error),
+    }
+}
+
+// The weights in a file if there is one, otherwise those the bots use
+fn start_weights(path: &str) -> Result<Weights, String> {
+    if std::path::Path::new(path).exists() {
+        Weights::load(path)
+    } else {
+        Ok(bot::weights())
+    }
+}
+
+// Learn weights by self play, continuing from the weights in the file if there are any
+fn train(games: usize, output: &str, depth: Option<u64>, rules: &RuleSet, seed: Option<u64>) {
+    let start = match start_weights(output) {
+        Ok(weights) => weights,
+        Err(error) => return eprintln!("{}", error),
+    };
+    let default = learn::Training::default();
+    let training = learn::Training {
+        games,
+        depth: depth.unwrap_or(default.depth),
+        rules: rules.clone(),
+        seed: seed.unwrap_or(default.seed),
+        ..default
+    };
+    println!("training with seed {}", training.seed);
+    let result = learn::train(&training, start, |game, weights| {
+        // Save now and then, so an interrupted training keeps its progress
+        if game % 10 == 0 || game == games {
+            println!("game {}/{}: {:?}", game, games, weights.0);
+            if let Err(error) = weights.save(output) {
+                eprintln!("{}", error);
+            }
+        }
+    });
+    let weights = match result {
+        Ok(weights) => weights,
+        Err(error) => return eprintln!("self play failed: {}", error),
+    };
+    if let Err(error) = weights.save(output) {
+        eprintln!("{}", error);
+    }
+}
+
+// Tune weights to predict the results of a collection of games
+fn tune(collection: &str, output: &str, rules: &RuleSet) {
+    let samples = match std::fs::read_to_string(collection)
+        .map_err(|error| format!("could not read {}: {}", collection, error))
+        .and_then(|text| tune::read_collection(&text, rules))
+    {
+        Ok(samples) if samples.is_empty() => return eprintln!("{} has no finished games", collection),
+        Ok(samples) => samples,
+        Err(error) => return eprintln!("{}", error),
+    };
+    let start = match start_weights(output) {
+        Ok(weights) => weights,
+        Err(error) => return eprintln!("{}", error),
+    };
+    println!("{} positions, error {:.5}", samples.len(), tune::error(&start, &samples));
+    let weights = tune::tune(start, &samples, |step, error, weights| {
+        println!("step {}: error {:.5} {:?}", step, error, weights.0);
+    });
+    if let Err(error) = weights.save(output) {
+        eprintln!("{}", error);
+    }
+}
+
+fn write_file(path: &str, content: &str) {
+    if let Err(error) = std::fs::write(path, content) {
+        eprintln!("could not write {}: {}", path, error);
+    }
+}
+
+// Serve the JSON API and the page to play in the browser on localhost, on the default port unless one is given
+#[cfg(feature = "serde")]
+fn http(port: Option<&str>, rules: RuleSet) {
+    let port = match port.map(|port| port.parse::<u16>()) {
+        Some(Ok(port)) => port,
+        Some(Err(_)) => return eprintln!("usage: second_best --http [port]"),
+        None => second_best::http::DEFAULT_PORT,
+    };
+    println!("Second Best is served on http://127.0.0.1:{}, open it in a browser to play", port);
+    if let Err(error) = second_best::http::run(port, rules) {
+        eprintln!("server failed: {}", error);
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn http(_port: Option<&str>, _rules: RuleSet) {
+    eprintln!("the HTTP API needs the serde feature, build with --features serde");
 }
@@ -0,0 +1,357 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    rc::Rc,
+};
+
+use crate::{
+    game::*,
+    io::IO,
+    notation,
+    player::{Decision, Player},
+    rules::RuleSet,
+};
+
+// Both instances run the same game in lockstep, each sending the decisions of its local player.
+//
+// Messages, one per line, turns in the notation of `notation`:
+//   hello <version> [w|b] <rules>
+//                              handshake, the host adds the colour it plays. Both send every rule as
+//                              <name> <value> pairs of `RuleSet::set` and refuse a peer with other rules
+//   propose <turn>             the turn proposed by the side to move
+//   secondbest yes|no          the decision of the opponent on the proposed turn
+//   retry <turn>               the turn played instead after second best was called
+//   resign                     the side to move resigns instead of proposing or retrying a turn
+//   gameover <reason>          the sender ends the game early, e.g. because the peer misbehaved
+
+pub const NETWORK_VERSION: u32 = 3;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Message {
+    Hello(u32, Option<Colour>, RuleSet),
+    Propose(Turn),
+    SecondBest(bool),
+    Retry(Turn),
+    Resign,
+    GameOver(String),
+}
+
+impl Message {
+    pub fn to_line(&self) -> String {
+        match self {
+            Message::Hello(version, colour, rules) => {
+                let colour = colour.map(|colour| format!(" {}", notation::colour_to_char(colour)));
+                format!("hello {}{} {}", version, colour.unwrap_or_default(), rules.settings_line())
+            }
+            Message::Propose(turn) => format!("propose {}", notation::turn_to_string(turn)),
+            Message::SecondBest(true) => "secondbest yes".to_owned(),
+            Message::SecondBest(false) => "secondbest no".to_owned(),
+            Message::Retry(turn) => format!("retry {}", notation::turn_to_string(turn)),
+            Message::Resign => "resign".to_owned(),
+            Message::GameOver(reason) => format!("gameover {}", reason),
+        }
+    }
+
+    // Parse a line sent by the player of the given colour
+    pub fn parse(line: &str, colour: Colour) -> Option<Message> {
+        let (command, argument) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match (command, argument) {
+            ("hello", argument) => {
+                let mut parts = argument.split_whitespace().peekable();
+                let version = parts.next()?.parse::<u32>().ok()?;
+                // Rule names are longer than a colour
+                let colour = match parts.next_if(|part| part.len() == 1) {
+                    Some(c) => Some(notation::parse_colour(c.chars().next()?).filter(|c| *c != Colour::Red)?),
+                    None => None,
+                };
+                let settings: Vec<&str> = parts.collect();
+                if !settings.len().is_multiple_of(2) {
+                    return None;
+                }
+                let mut rules = RuleSet::default();
+                for pair in settings.chunks(2) {
+                    rules.set(pair[0], pair[1]).ok()?;
+                }
+                Some(Message::Hello(version, colour, rules))
+            }
+            ("propose", turn) => Some(Message::Propose(notation::parse_turn(turn, colour)?)),
+            ("secondbest", "yes") => Some(Message::SecondBest(true)),
+            ("secondbest", "no") => Some(Message::SecondBest(false)),
+            ("retry", turn) => Some(Message::Retry(notation::parse_turn(turn, colour)?)),
+            ("resign", "") => Some(Message::Resign),
+            ("gameover", reason) => Some(Message::GameOver(reason.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+pub struct Connection {
+    reader: RefCell<BufReader<TcpStream>>,
+    writer: RefCell<TcpStream>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: RefCell::new(BufReader::new(stream.try_clone()?)),
+            writer: RefCell::new(stream),
+        })
+    }
+
+    pub fn send(&self, message: &Message) -> io::Result<()> {
+        let mut writer = self.writer.borrow_mut();
+        writeln!(writer, "{}", message.to_line())?;
+        writer.flush()
+    }
+
+    // Receive a message sent by the player of the given colour
+    pub fn receive(&self, colour: Colour) -> io::Result<Message> {
+        let mut line = String::new();
+        if self.reader.borrow_mut().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+        }
+        Message::parse(&line, colour).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid message {}", line.trim()))
+        })
+    }
+}
+
+// Wait for a peer to connect, returns the connection and the colour the peer plays.
+// The port is opened on all interfaces, so players on other machines of the network can join
+pub fn host(port: u16, colour: Colour, rules: &RuleSet) -> io::Result<(Rc<Connection>, Colour)> {
+    accept(&TcpListener::bind(("0.0.0.0", port))?, colour, rules)
+}
+
+// Wait for a peer on a listener that is already bound, e.g. to localhost
+pub fn accept(listener: &TcpListener, colour: Colour, rules: &RuleSet) -> io::Result<(Rc<Connection>, Colour)> {
+    let (stream, _) = listener.accept()?;
+    let connection = Connection::new(stream)?;
+    connection.send(&Message::Hello(NETWORK_VERSION, Some(colour), rules.clone()))?;
+    match connection.receive(colour.opposite())? {
+        Message::Hello(NETWORK_VERSION, None, peer_rules) if peer_rules == *rules => {
+            Ok((Rc::new(connection), colour.opposite()))
+        }
+        Message::Hello(NETWORK_VERSION, None, peer_rules) => Err(rules_mismatch(&peer_rules, rules)),
+        Message::Hello(version, _, _) => Err(version_mismatch(version)),
+        message => Err(unexpected(&message)),
+    }
+}
+
+// Connect to a host, returns the connection and the colour this side plays
+pub fn join(address: impl ToSocketAddrs, rules: &RuleSet) -> io::Result<(Rc<Connection>, Colour)> {
+    let connection = Connection::new(TcpStream::connect(address)?)?;
+    match connection.receive(Colour::White)? {
+        Message::Hello(NETWORK_VERSION, Some(host_colour), host_rules) => {
+            // The host checks the rules as well, so both sides learn about a mismatch
+            connection.send(&Message::Hello(NETWORK_VERSION, None, rules.clone()))?;
+            if host_rules != *rules {
+                return Err(rules_mismatch(&host_rules, rules));
+            }
+            Ok((Rc::new(connection), host_colour.opposite()))
+        }
+        Message::Hello(version, _, _) => Err(version_mismatch(version)),
+        message => Err(unexpected(&message)),
+    }
+}
+
+// Name the rules that differ
+fn rules_mismatch(peer_rules: &RuleSet, rules: &RuleSet) -> io::Error {
+    let differences: Vec<String> = peer_rules
+        .settings()
+        .into_iter()
+        .zip(rules.settings())
+        .filter(|(peer, own)| peer != own)
+        .map(|((name, peer), (_, own))| format!("{} {} instead of {}", name, peer, own))
+        .collect();
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("peer plays with other rules: {}", differences.join(", ")),
+    )
+}
+
+fn version_mismatch(version: u32) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("peer speaks version {}, expected {}", version, NETWORK_VERSION),
+    )
+}
+
+fn unexpected(message: &Message) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message {}", message.to_line()),
+    )
+}
+
+// The player on the other end of the connection, every turn it sends is checked before it is played
+pub struct RemotePlayer {
+    colour: Colour,
+    connection: Rc<Connection>,
+    forfeit_reason: Option<String>,
+}
+
+impl RemotePlayer {
+    pub fn new(colour: Colour, connection: Rc<Connection>) -> Self {
+        Self {
+            colour,
+            connection,
+            forfeit_reason: None,
+        }
+    }
+
+    // Stop playing with the peer, it is told why unless it already left
+    fn forfeit(&mut self, reason: String, tell_peer: bool) {
+        if tell_peer {
+            let _ = self.connection.send(&Message::GameOver(reason.clone()));
+        }
+        if self.forfeit_reason.is_none() {
+            self.forfeit_reason = Some(reason);
+        }
+    }
+
+    fn receive(&mut self) -> Option<Message> {
+        if self.forfeit_reason.is_some() {
+            return None;
+        }
+        match self.connection.receive(self.colour) {
+            Ok(Message::GameOver(reason)) => {
+                self.forfeit(format!("peer ended the game: {}", reason), false);
+                None
+            }
+            Ok(message) => Some(message),
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                self.forfeit(error.to_string(), true);
+                None
+            }
+            Err(error) => {
+                self.forfeit(format!("connection lost: {}", error), true);
+                None
+            }
+        }
+    }
+
+    // Receive the decision of the peer and check its turn can be played in the game
+    fn receive_turn(&mut self, game: &Game) -> Option<Decision> {
+        let second_best = matches!(game.phase, Phase::Retry(_));
+        let turn = match (self.receive()?, second_best) {
+            (Message::Propose(turn), false) => turn,
+            (Message::Retry(turn), true) => turn,
+            (Message::Resign, _) => return Some(Decision::Resign),
+            (message, _) => {
+                self.forfeit(format!("unexpected message {}", message.to_line()), true);
+                return None;
+            }
+        };
+        if !game.legal_turns().contains(&turn) {
+            self.forfeit(format!("illegal turn {}", notation::turn_to_string(&turn)), true);
+            return None;
+        }
+        Some(Decision::Play(turn))
+    }
+}
+
+impl Player for RemotePlayer {
+    fn get_colour(&self) -> Colour {
+        self.colour
+    }
+
+    // A peer that misbehaves forfeits, the decision returned then is never used
+    fn ask_turn(&mut self, game: &Game) -> Decision {
+        self.receive_turn(game).unwrap_or(Decision::Resign)
+    }
+
+    fn ask_second_best(&mut self, _game: &Game, _turn: &Turn) -> bool {
+        match self.receive() {
+            Some(Message::SecondBest(b)) => b,
+            Some(message) => {
+                self.forfeit(format!("unexpected message {}", message.to_line()), true);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn forfeit_reason(&self) -> Option<String> {
+        self.forfeit_reason.clone()
+    }
+}
+
+// A local player whose decisions are sent to the peer, only valid turns are sent
+pub struct NetworkedPlayer {
+    player: Box<dyn Player>,
+    connection: Rc<Connection>,
+}
+
+impl NetworkedPlayer {
+    pub fn new(player: Box<dyn Player>, connection: Rc<Connection>) -> Self {
+        Self { player, connection }
+    }
+
+    fn send(&self, message: &Message) {
+        // A lost connection shows up when the peer is asked for its next decision
+        let _ = self.connection.send(message);
+    }
+}
+
+impl Player for NetworkedPlayer {
+    fn get_colour(&self) -> Colour {
+        self.player.get_colour()
+    }
+
+    fn ask_turn(&mut self, game: &Game) -> Decision {
+        loop {
+            let decision = self.player.ask_turn(game);
+            if self.player.forfeit_reason().is_some() {
+                self.send(&Message::GameOver(format!("{:?} forfeits", self.get_colour())));
+                return decision;
+            }
+            match decision {
+                Decision::Resign => self.send(&Message::Resign),
+                Decision::Play(ref turn) if game.legal_turns().contains(turn) => match game.phase {
+                    Phase::Retry(_) => self.send(&Message::Retry(turn.clone())),
+                    _ => self.send(&Message::Propose(turn.clone())),
+                },
+                Decision::Play(_) => {
+                    IO::invalid_turn();
+                    continue;
+                }
+            }
+            return decision;
+        }
+    }
+
+    fn ask_second_best(&mut self, game: &Game, turn: &Turn) -> bool {
+        let b = self.player.ask_second_best(game, turn);
+        if self.player.forfeit_reason().is_some() {
+            self.send(&Message::GameOver(format!("{:?} forfeits", self.get_colour())));
+        } else {
+            self.send(&Message::SecondBest(b));
+        }
+        b
+    }
+
+    fn turn_proposed(&mut self, game: &Game, turn: &Turn) {
+        self.player.turn_proposed(game, turn)
+    }
+
+    fn second_best_decided(&mut self, game: &Game, colour: Colour, called: bool) {
+        self.player.second_best_decided(game, colour, called)
+    }
+
+    fn turn_played(&mut self, game: &Game, turn: &Turn) {
+        self.player.turn_played(game, turn)
+    }
+
+    fn game_over(&mut self, game: &Game, outcome: &Outcome) {
+        self.player.game_over(game, outcome)
+    }
+
+    fn forfeit_reason(&self) -> Option<String> {
+        self.player.forfeit_reason()
+    }
+
+    fn forfeit_end_reason(&self) -> EndReason {
+        self.player.forfeit_end_reason()
+    }
+}
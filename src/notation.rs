@@ -0,0 +1,155 @@
+use crate::game::*;
+
+// Turns are written with the stacks counted from 1 like on the printed board:
+// "3" places a piece on stack 3, "2-6" moves a piece from stack 2 to stack 6.
+// A round where second best was called is written as both turns: "3/5".
+
+pub fn turn_to_string(turn: &Turn) -> String {
+    match *turn {
+        Turn::Place(_, idx) => format!("{}", idx + 1),
+        Turn::Move(_, idx1, idx2) => format!("{}-{}", idx1 + 1, idx2 + 1),
+    }
+}
+
+pub fn parse_turn(s: &str, colour: Colour) -> Option<Turn> {
+    let parse_idx = |s: &str| match s.parse::<usize>() {
+        Ok(n) if (1..=MAX_STACKS).contains(&n) => Some(n - 1),
+        _ => None,
+    };
+    match s.split_once('-') {
+        Some((from, to)) => Some(Turn::Move(colour, parse_idx(from)?, parse_idx(to)?)),
+        None => Some(Turn::Place(colour, parse_idx(s)?)),
+    }
+}
+
+pub fn round_to_string(round: &Round) -> String {
+    match &round.second_turn {
+        Some(second_turn) => format!(
+            "{}/{}",
+            turn_to_string(&round.proposed),
+            turn_to_string(second_turn)
+        ),
+        None => turn_to_string(&round.proposed),
+    }
+}
+
+pub fn parse_round(s: &str, colour: Colour) -> Option<Round> {
+    match s.split_once('/') {
+        Some((proposed, second_turn)) => Some(Round {
+            proposed: parse_turn(proposed, colour)?,
+            second_turn: Some(parse_turn(second_turn, colour)?),
+        }),
+        None => Some(Round {
+            proposed: parse_turn(s, colour)?,
+            second_turn: None,
+        }),
+    }
+}
+
+// A record is the rounds of a game separated by whitespace, White makes the first turn
+// and the colours take turns in the order White, Black, Red
+pub fn record_to_string(rounds: &[Round]) -> String {
+    rounds
+        .iter()
+        .map(round_to_string)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn parse_record(s: &str, players: usize) -> Option<Vec<Round>> {
+    let mut colour = Colour::White;
+    let mut rounds = Vec::new();
+    for token in s.split_whitespace() {
+        rounds.push(parse_round(token, colour)?);
+        colour = colour.next(players);
+    }
+    Some(rounds)
+}
+
+// A board is written as its stacks separated by '/', each stack lists its pieces from the bottom up
+// with 'w', 'b' and 'r', an empty stack is written as '-': "w/wb/-/-/b/-/-/-"
+pub fn board_to_string(board: &Board) -> String {
+    board
+        .places
+        .iter()
+        .map(|place| {
+            if place.is_empty() {
+                return "-".to_owned();
+            }
+            place
+                .0
+                .iter()
+                .filter_map(|piece| match piece {
+                    Piece::Piece(colour) => Some(colour_to_char(*colour)),
+                    Piece::Blank => None,
+                })
+                .collect()
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// Parse a board with the given dimensions, the string has to have one entry for every stack
+pub fn parse_board(s: &str, config: BoardConfig) -> Option<Board> {
+    let stacks: Vec<&str> = s.split('/').collect();
+    if stacks.len() != config.stacks {
+        return None;
+    }
+    let mut board = Board::with_config(config);
+    for (place, stack) in board.places.iter_mut().zip(stacks) {
+        if stack == "-" {
+            continue;
+        }
+        if stack.len() > config.height {
+            return None;
+        }
+        for c in stack.chars() {
+            place.add_piece(&parse_colour(c)?);
+        }
+    }
+    Some(board)
+}
+
+pub fn colour_to_char(colour: Colour) -> char {
+    match colour {
+        Colour::White => 'w',
+        Colour::Black => 'b',
+        Colour::Red => 'r',
+    }
+}
+
+pub fn parse_colour(c: char) -> Option<Colour> {
+    match c {
+        'w' => Some(Colour::White),
+        'b' => Some(Colour::Black),
+        'r' => Some(Colour::Red),
+        _ => None,
+    }
+}
+
+pub fn end_reason_to_string(reason: EndReason) -> &'static str {
+    match reason {
+        EndReason::Stack => "stack",
+        EndReason::RingLine => "ringline",
+        EndReason::Simultaneous => "simultaneous",
+        EndReason::Repetition => "repetition",
+        EndReason::Resignation => "resignation",
+        EndReason::Timeout => "timeout",
+        EndReason::NoLegalMove => "nolegalmove",
+        EndReason::Forfeit => "forfeit",
+    }
+}
+
+// An outcome as "<w|b|draw> <reason> [stacks]", stacks numbered from 1 and joined by ',' e.g. "w ringline 2,3,4,5"
+pub fn outcome_to_string(outcome: &Outcome) -> String {
+    let result = match outcome.result {
+        EndState::Win(colour) => colour_to_char(colour).to_string(),
+        EndState::Draw => "draw".to_owned(),
+    };
+    let mut s = format!("{} {}", result, end_reason_to_string(outcome.reason));
+    if !outcome.stacks.is_empty() {
+        let stacks: Vec<String> = outcome.stacks.iter().map(|i| (i + 1).to_string()).collect();
+        s += &format!(" {}", stacks.join(","));
+    }
+    s
+}
@@ -0,0 +1,45 @@
+use crate::{game::*, rules::RuleSet};
+
+// Count the positions reached by playing every legal turn for a number of rounds, to check turn generation.
+// With vetoes a round also branches into second best being called on the proposed turn and each of the
+// other turns being played instead, so a round with n turns has n * n outcomes instead of n.
+// A finished game has no turns, so its branch only counts if it ends in the last round.
+// Limits on second best calls and repetitions depend on the history of a game and are not taken into account.
+pub fn perft(board: &Board, rules: &RuleSet, colour: Colour, depth: u32, vetoes: bool) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    divide(board, rules, colour, depth, vetoes)
+        .into_iter()
+        .map(|(_, nodes)| nodes)
+        .sum()
+}
+
+// The number of positions below every turn of the first round, as shown by the perft command
+pub fn divide(board: &Board, rules: &RuleSet, colour: Colour, depth: u32, vetoes: bool) -> Vec<(Turn, u64)> {
+    if depth == 0 || board.is_won().is_some() {
+        return Vec::new();
+    }
+    let turns = rules.possible_turns(board, colour);
+    let ways = ways_to_play(turns.len(), rules, vetoes);
+    let next = colour.next(board.config.players);
+    turns
+        .into_iter()
+        .map(|turn| {
+            let mut new_board = board.clone();
+            new_board.do_turn(&turn);
+            let nodes = perft(&new_board, rules, next, depth - 1, vetoes);
+            (turn, nodes * ways)
+        })
+        .collect()
+}
+
+// A turn is played by letting it pass or by playing it after second best was called on another turn
+fn ways_to_play(turns: usize, rules: &RuleSet, vetoes: bool) -> u64 {
+    match (vetoes, rules.replay_vetoed) {
+        (false, _) => 1,
+        (true, true) => turns as u64 + 1,
+        (true, false) if turns > 1 => turns as u64,
+        (true, false) => 1,
+    }
+}
@@ -1,13 +1,46 @@
 use crate::{
-    game::{Board, Colour, Turn},
+    game::{Colour, EndReason, Game, Outcome, Phase, Turn},
     io::IO,
 };
 
+// What a player does when it is asked for a turn
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Decision {
+    Play(Turn),
+    Resign,
+}
+
+// A participant in a game driven by `Game::gameloop`. Players see the whole game when deciding
+// and are told about every step of it, including the ones of the other players
 pub trait Player {
     fn get_colour(&self) -> Colour;
-    fn ask_put_piece(&self, board: &Board, second_best: bool) -> usize;
-    fn ask_move_piece(&self, board: &Board, second_best: bool) -> (usize, usize);
-    fn ask_second_best(&self, board: &Board, turn: &Turn) -> bool;
+
+    // Return the turn to play, after second best the vetoed turn is in `Phase::Retry` of the game
+    fn ask_turn(&mut self, game: &Game) -> Decision;
+
+    // Return whether to call second best on the turn proposed by another player
+    fn ask_second_best(&mut self, game: &Game, turn: &Turn) -> bool;
+
+    // A player proposed a turn, the others decide on second best next
+    fn turn_proposed(&mut self, _game: &Game, _turn: &Turn) {}
+
+    // A player decided whether to call second best on the proposed turn
+    fn second_best_decided(&mut self, _game: &Game, _colour: Colour, _called: bool) {}
+
+    // A round ended with the turn that was played, the game already contains it
+    fn turn_played(&mut self, _game: &Game, _turn: &Turn) {}
+
+    fn game_over(&mut self, _game: &Game, _outcome: &Outcome) {}
+
+    // Reason why the player lost the game without finishing it, e.g. a crashed engine
+    fn forfeit_reason(&self) -> Option<String> {
+        None
+    }
+
+    // How the forfeit is recorded in the outcome of the game
+    fn forfeit_end_reason(&self) -> EndReason {
+        EndReason::Forfeit
+    }
 }
 
 pub struct Person {
@@ -25,15 +58,17 @@ impl Player for Person {
         self.colour
     }
 
-    fn ask_put_piece(&self, _board: &Board, _second_best: bool) -> usize {
-        IO::ask_put_piece(self.colour)
-    }
-
-    fn ask_move_piece(&self, _board: &Board, _second_best: bool) -> (usize, usize) {
-        IO::ask_move_piece(self.colour)
+    fn ask_turn(&mut self, game: &Game) -> Decision {
+        let second_best = matches!(game.phase, Phase::Retry(_));
+        let turn = if game.board.is_placing() {
+            IO::ask_put_piece(self.colour, &game.rules, &game.board, second_best).map(|i| Turn::Place(self.colour, i))
+        } else {
+            IO::ask_move_piece(self.colour, &game.rules, &game.board, second_best).map(|(i, j)| Turn::Move(self.colour, i, j))
+        };
+        turn.map_or(Decision::Resign, Decision::Play)
     }
 
-    fn ask_second_best(&self, _board: &Board, _turn: &Turn) -> bool {
-        IO::ask_second_best()
+    fn ask_second_best(&mut self, game: &Game, turn: &Turn) -> bool {
+        IO::ask_second_best(self.colour, &game.rules, &game.board, turn)
     }
 }
@@ -0,0 +1,352 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    bot::{self, Bot},
+    game::*,
+    notation, perft,
+    rules::RuleSet,
+    search::{SearchStats, TranspositionTable},
+};
+
+// Line based protocol to drive the engine as a subprocess, turns use the notation of `notation`.
+//
+// GUI to engine:
+//   sbp                                    identify, answered with id lines and "sbpok"
+//   isready                                answered with "readyok"
+//   newgame                                reset to the empty board
+//   rules <name> <value>...                change rules, see `RuleSet::set`
+//   threads <n>|all                        number of threads to search with
+//   position startpos [rounds <round>...]  set the board from the rounds played so far
+//   position board <board> <w|b>           set the board directly and the side to move
+//   go [depth <n>] [movetime <ms>] [vetoed <turn>]
+//                                          search for the side to move, a vetoed turn is not played
+//   secondbest? <turn> [depth <n>]         should second best be called on the turn of the side to move
+//   perft <depth> [vetoes]                 count the positions reached by all turns, see `perft::perft`
+//   stop                                   end the search, the best turn found so far is sent
+//   quit
+//
+// Engine to GUI:
+//   info depth <n> score <score> nodes <n> pv <turn>...
+//   info string stats <stats>              work done by the search, see `SearchStats`
+//   info string <message>
+//   bestmove <turn>
+//   secondbest yes|no
+//   perft <turn> <nodes>                   one line per turn of the side to move, followed by
+//   perft total <nodes>
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Search depth used when a command does not limit the search itself
+pub const DEFAULT_DEPTH: u64 = 3;
+
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+pub struct Engine {
+    board: Board,
+    to_move: Colour,
+    rules: RuleSet,
+    threads: usize,
+    search: Option<Search>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::with_rules(RuleSet::default())
+    }
+
+    pub fn with_rules(rules: RuleSet) -> Self {
+        Self {
+            board: Board::with_config(rules.board),
+            to_move: Colour::White,
+            rules,
+            threads: bot::threads(),
+            search: None,
+        }
+    }
+
+    // Handle a single command, returns false when the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("sbp") => {
+                send(&format!("id name {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")));
+                send(&format!("id protocol {}", PROTOCOL_VERSION));
+                send("sbpok");
+            }
+            Some("isready") => send("readyok"),
+            Some("newgame") => {
+                self.stop_search();
+                self.board = Board::with_config(self.rules.board);
+                self.to_move = Colour::White;
+            }
+            Some("rules") => {
+                self.stop_search();
+                if let Err(error) = self.set_rules(tokens.collect()) {
+                    send(&format!("info string {}", error));
+                }
+            }
+            Some("threads") => {
+                self.stop_search();
+                match tokens.next().and_then(bot::parse_threads) {
+                    Some(threads) => self.threads = threads,
+                    None => send("info string expected threads <n>|all"),
+                }
+            }
+            Some("position") => {
+                self.stop_search();
+                if let Err(error) = self.set_position(tokens.collect()) {
+                    send(&format!("info string {}", error));
+                }
+            }
+            Some("go") => {
+                self.stop_search();
+                if let Err(error) = self.go(tokens.collect()) {
+                    send(&format!("info string {}", error));
+                }
+            }
+            Some("secondbest?") => {
+                if let Err(error) = self.second_best(tokens.collect()) {
+                    send(&format!("info string {}", error));
+                }
+            }
+            Some("perft") => {
+                if let Err(error) = self.perft(tokens.collect()) {
+                    send(&format!("info string {}", error));
+                }
+            }
+            Some("stop") => self.stop_search(),
+            Some("quit") => {
+                self.stop_search();
+                return false;
+            }
+            Some(command) => send(&format!("info string unknown command {}", command)),
+            None => {}
+        }
+        true
+    }
+
+    fn set_rules(&mut self, tokens: Vec<&str>) -> Result<(), String> {
+        if tokens.is_empty() || !tokens.len().is_multiple_of(2) {
+            return Err("expected rules <name> <value>...".to_owned());
+        }
+        let mut rules = self.rules.clone();
+        for pair in tokens.chunks(2) {
+            rules.set(pair[0], pair[1])?;
+        }
+        if !rules.board.is_valid() {
+            return Err("invalid board dimensions".to_owned());
+        }
+        if rules.board.players != 2 {
+            return Err("the protocol is for two players".to_owned());
+        }
+        // The position is reset when the board changes
+        if rules.board != self.rules.board {
+            self.board = Board::with_config(rules.board);
+            self.to_move = Colour::White;
+        }
+        self.rules = rules;
+        Ok(())
+    }
+
+    fn set_position(&mut self, tokens: Vec<&str>) -> Result<(), String> {
+        match tokens.as_slice() {
+            ["startpos"] => {}
+            ["startpos", "rounds", ..] => {}
+            ["board", board, colour] => {
+                self.board = notation::parse_board(board, self.rules.board).ok_or_else(|| format!("invalid board {}", board))?;
+                self.to_move = match *colour {
+                    "w" => Colour::White,
+                    "b" => Colour::Black,
+                    _ => return Err(format!("invalid colour {}", colour)),
+                };
+                return Ok(());
+            }
+            _ => return Err("expected position startpos [rounds <round>...] or position board <board> <w|b>".to_owned()),
+        }
+        let record = tokens.get(2..).unwrap_or_default().join(" ");
+        let rounds = notation::parse_record(&record, self.rules.board.players).ok_or_else(|| format!("invalid rounds {}", record))?;
+        // The rounds are replayed as a game, so the phase and the calls left are checked as well
+        let game = Game::from_rounds_with_rules(&rounds, self.rules.clone()).map_err(|error| format!("illegal rounds: {}", error))?;
+        self.board = game.board.clone();
+        self.to_move = game.to_move();
+        Ok(())
+    }
+
+    fn go(&mut self, tokens: Vec<&str>) -> Result<(), String> {
+        let mut depth = None;
+        let mut movetime = None;
+        let mut vetoed = None;
+        let mut tokens = tokens.into_iter();
+        while let Some(token) = tokens.next() {
+            let value = tokens
+                .next()
+                .ok_or_else(|| format!("missing value for {}", token))?;
+            match token {
+                "depth" => depth = Some(value.parse::<u64>().map_err(|_| format!("invalid depth {}", value))?),
+                "movetime" => movetime = Some(value.parse::<u64>().map_err(|_| format!("invalid movetime {}", value))?),
+                "vetoed" => {
+                    vetoed = Some(
+                        notation::parse_turn(value, self.to_move)
+                            .ok_or_else(|| format!("invalid turn {}", value))?,
+                    )
+                }
+                _ => return Err(format!("unknown go parameter {}", token)),
+            }
+        }
+        if self.rules.is_won(&self.board, self.to_move.opposite()).is_some() {
+            return Err("the game is over".to_owned());
+        }
+        let vetoed = vetoed.filter(|_| !self.rules.replay_vetoed);
+        // Without a depth a timed search deepens until stopped, otherwise use the default depth
+        let max_depth = depth.unwrap_or(if movetime.is_some() { u64::MAX } else { DEFAULT_DEPTH });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        if let Some(movetime) = movetime {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(movetime));
+                stop.store(true, Ordering::Relaxed);
+            });
+        }
+        let board = self.board.clone();
+        let colour = self.to_move;
+        let search_stop = stop.clone();
+        let rules = self.rules.clone();
+        let threads = self.threads;
+        let handle = thread::spawn(move || search(board, colour, &rules, threads, max_depth, vetoed, search_stop));
+        self.search = Some(Search { stop, handle });
+        Ok(())
+    }
+
+    fn second_best(&mut self, tokens: Vec<&str>) -> Result<(), String> {
+        let (turn, depth) = match tokens.as_slice() {
+            [turn] => (*turn, DEFAULT_DEPTH),
+            [turn, "depth", depth] => (*turn, depth.parse::<u64>().map_err(|_| format!("invalid depth {}", depth))?),
+            _ => return Err("expected secondbest? <turn> [depth <n>]".to_owned()),
+        };
+        let turn = notation::parse_turn(turn, self.to_move).ok_or_else(|| format!("invalid turn {}", turn))?;
+        if !self.rules.is_possible_turn(&self.board, &turn) {
+            return Err(format!("illegal turn {}", notation::turn_to_string(&turn)));
+        }
+        let bot = Bot::new(self.to_move.opposite(), depth)
+            .with_rules(self.rules.clone())
+            .with_threads(self.threads);
+        if bot.would_call_second_best(&self.board, &turn) {
+            send("secondbest yes");
+        } else {
+            send("secondbest no");
+        }
+        Ok(())
+    }
+
+    fn perft(&self, tokens: Vec<&str>) -> Result<(), String> {
+        let (depth, vetoes) = match tokens.as_slice() {
+            [depth] => (*depth, false),
+            [depth, "vetoes"] => (*depth, true),
+            _ => return Err("expected perft <depth> [vetoes]".to_owned()),
+        };
+        let depth = depth.parse::<u32>().map_err(|_| format!("invalid depth {}", depth))?;
+        let mut total = 0;
+        for (turn, nodes) in perft::divide(&self.board, &self.rules, self.to_move, depth, vetoes) {
+            send(&format!("perft {} {}", notation::turn_to_string(&turn), nodes));
+            total += nodes;
+        }
+        send(&format!("perft total {}", if depth == 0 { 1 } else { total }));
+        Ok(())
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            let _ = search.handle.join();
+        }
+    }
+}
+
+// Deepen the search one ply at a time until the depth is reached or the search is stopped
+fn search(board: Board, colour: Colour, rules: &RuleSet, threads: usize, max_depth: u64, vetoed: Option<Turn>, stop: Arc<AtomicBool>) {
+    let mut best: Option<Turn> = None;
+    let mut depth = 0;
+    let table = Arc::new(TranspositionTable::new());
+    let mut stats = SearchStats::default();
+    while depth <= max_depth {
+        // The shallowest search always completes so there is a turn to send
+        let bot = if depth == 0 {
+            Bot::new(colour, depth)
+        } else {
+            Bot::new(colour, depth).with_stop(stop.clone())
+        }
+        .with_rules(rules.clone())
+        .with_threads(threads)
+        .with_table(table.clone());
+        let scored_turns = bot.score_turns(&board, colour);
+        stats.add(&bot.stats());
+        if bot.is_stopped() {
+            break;
+        }
+        let Some((turn, score)) = scored_turns
+            .into_iter()
+            .filter(|(turn, _)| Some(turn) != vetoed.as_ref())
+            .fold(None, |best: Option<(Turn, f64)>, (turn, score)| match best {
+                Some(best) if best.1 >= score => Some(best),
+                _ => Some((turn, score)),
+            })
+        else {
+            break;
+        };
+        let pv = bot.principal_variation(&board, &turn);
+        send(&format!(
+            "info depth {} score {:.2} nodes {} pv {}",
+            depth,
+            score,
+            bot.stats().nodes + bot.stats().quiescence_nodes,
+            pv.iter().map(notation::turn_to_string).collect::<Vec<String>>().join(" ")
+        ));
+        best = Some(turn);
+        depth += 1;
+    }
+    send(&format!("info string stats {}", stats));
+    match best {
+        Some(turn) => send(&format!("bestmove {}", notation::turn_to_string(&turn))),
+        None => send("bestmove none"),
+    }
+}
+
+fn send(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+// Run the engine on stdin and stdout until told to quit
+pub fn run(rules: RuleSet) {
+    let mut engine = Engine::with_rules(rules);
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) => {
+                if !engine.handle_command(&line) {
+                    return;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    engine.stop_search();
+}
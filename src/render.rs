@@ -0,0 +1,272 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{game::*, notation};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Style {
+    Unicode,
+    Ascii,
+    Ansi,
+}
+
+// The style boards are printed in, chosen once on the command line
+static STYLE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_style(style: Style) {
+    STYLE.store(style as u8, Ordering::Relaxed);
+}
+
+pub fn style() -> Style {
+    match STYLE.load(Ordering::Relaxed) {
+        1 => Style::Ascii,
+        2 => Style::Ansi,
+        _ => Style::Unicode,
+    }
+}
+
+impl Style {
+    pub fn parse(s: &str) -> Option<Style> {
+        match s {
+            "unicode" => Some(Style::Unicode),
+            "ascii" => Some(Style::Ascii),
+            "ansi" => Some(Style::Ansi),
+            _ => None,
+        }
+    }
+
+    pub fn renderer(&self) -> Box<dyn Renderer> {
+        match self {
+            Style::Unicode => Box::new(UnicodeRenderer),
+            Style::Ascii => Box::new(AsciiRenderer),
+            Style::Ansi => Box::new(AnsiRenderer),
+        }
+    }
+}
+
+// Stacks a renderer may draw attention to
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Highlight {
+    pub last_turn: Option<Turn>,
+    pub winning_stacks: Vec<usize>,
+}
+
+impl Highlight {
+    pub fn for_board(board: &Board, last_turn: Option<&Turn>) -> Self {
+        Self {
+            last_turn: last_turn.cloned(),
+            winning_stacks: board.outcome().map(|outcome| outcome.stacks).unwrap_or_default(),
+        }
+    }
+
+    pub fn is_last_turn(&self, idx: usize) -> bool {
+        match self.last_turn {
+            Some(Turn::Place(_, i)) => i == idx,
+            Some(Turn::Move(_, i, j)) => i == idx || j == idx,
+            None => false,
+        }
+    }
+}
+
+pub trait Renderer {
+    fn piece(&self, piece: &Piece) -> String;
+
+    fn stack(&self, place: &Place, _idx: usize, _highlight: &Highlight) -> String {
+        format!("[{}]", self.pieces(place))
+    }
+
+    fn pieces(&self, place: &Place) -> String {
+        place.0.iter().map(|piece| self.piece(piece)).collect()
+    }
+
+    // Draw the ring with stack 1 at the bottom left, going up the left side and down the right side
+    fn board(&self, board: &Board, highlight: &Highlight) -> String {
+        let stacks = board.places.len();
+        let stack = |idx: usize| self.stack(&board.places[idx], idx, highlight);
+        let width = stacks.to_string().len();
+        // The left side holds the first half of the stacks, an odd stack out goes there too
+        let rows = stacks.div_ceil(2);
+        let mut s = String::new();
+        for row in 0..rows {
+            let (left, right) = (rows - 1 - row, rows + row);
+            // The top and bottom rows are drawn closer together to suggest a ring
+            let edge = row == 0 || row == rows - 1;
+            let (outer, inner) = if edge { ("    ", " ") } else { ("   ", "   ") };
+            if right < stacks {
+                s += &format!(
+                    "{:<width$}{outer}{}{inner}{}{outer}{}\n",
+                    left + 1,
+                    stack(left),
+                    stack(right),
+                    right + 1
+                );
+            } else {
+                s += &format!("{:<width$}{outer}{}\n", left + 1, stack(left));
+            }
+        }
+        s
+    }
+}
+
+pub struct UnicodeRenderer;
+
+impl Renderer for UnicodeRenderer {
+    fn piece(&self, piece: &Piece) -> String {
+        match piece {
+            Piece::Blank => " ".to_owned(),
+            Piece::Piece(Colour::White) => "□".to_owned(),
+            Piece::Piece(Colour::Black) => "■".to_owned(),
+            Piece::Piece(Colour::Red) => "▲".to_owned(),
+        }
+    }
+}
+
+pub struct AsciiRenderer;
+
+impl Renderer for AsciiRenderer {
+    fn piece(&self, piece: &Piece) -> String {
+        match piece {
+            Piece::Blank => ".".to_owned(),
+            Piece::Piece(Colour::White) => "O".to_owned(),
+            Piece::Piece(Colour::Black) => "X".to_owned(),
+            Piece::Piece(Colour::Red) => "+".to_owned(),
+        }
+    }
+}
+
+// Coloured pieces, the stacks of the last turn in yellow and a winning line in green
+pub struct AnsiRenderer;
+
+const RESET: &str = "\x1b[0m";
+
+impl Renderer for AnsiRenderer {
+    fn piece(&self, piece: &Piece) -> String {
+        match piece {
+            Piece::Blank => " ".to_owned(),
+            Piece::Piece(Colour::White) => format!("\x1b[1;97mO{}", RESET),
+            Piece::Piece(Colour::Black) => format!("\x1b[1;34mX{}", RESET),
+            Piece::Piece(Colour::Red) => format!("\x1b[1;31mR{}", RESET),
+        }
+    }
+
+    fn stack(&self, place: &Place, idx: usize, highlight: &Highlight) -> String {
+        let pieces = self.pieces(place);
+        let bracket = if highlight.winning_stacks.contains(&idx) {
+            "\x1b[1;42m"
+        } else if highlight.is_last_turn(idx) {
+            "\x1b[1;33m"
+        } else {
+            return format!("[{}]", pieces);
+        };
+        format!("{bracket}[{RESET}{pieces}{bracket}]{RESET}")
+    }
+}
+
+// Size of a single board in SVG output
+const SVG_BOARD_WIDTH: u32 = 220;
+const SVG_BOARD_HEIGHT: u32 = 250;
+const SVG_BOARDS_PER_ROW: u32 = 4;
+
+// Draw one board as an SVG group at an offset, with an optional caption below it
+fn svg_board_group(board: &Board, highlight: &Highlight, x: u32, y: u32, caption: &str) -> String {
+    let (cx, cy, radius) = (110.0, 115.0, 80.0);
+    let mut svg = format!("<g transform=\"translate({} {})\">\n", x, y);
+    let step = 360.0 / board.places.len() as f64;
+    // Pieces are drawn 20 apart from the bottom up, centred on the position of the stack
+    let height = 20.0 * board.config.height as f64 + 4.0;
+    for (idx, place) in board.places.iter().enumerate() {
+        let angle = (270.0 - step / 2.0 - step * idx as f64).to_radians();
+        let (sx, sy) = (cx + radius * angle.cos(), cy - radius * angle.sin());
+        let outline = if highlight.winning_stacks.contains(&idx) {
+            "#2a2"
+        } else if highlight.is_last_turn(idx) {
+            "#e90"
+        } else {
+            "#888"
+        };
+        svg += &format!(
+            "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"24\" height=\"{}\" rx=\"4\" fill=\"#dcb\" stroke=\"{}\" stroke-width=\"3\"/>\n",
+            sx - 12.0,
+            sy - height / 2.0,
+            height,
+            outline
+        );
+        svg += &format!(
+            "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" text-anchor=\"middle\">{}</text>\n",
+            sx,
+            sy + height / 2.0 + 14.0,
+            idx + 1
+        );
+        // Pieces from the bottom of the stack up
+        for (slot, piece) in place.0.iter().enumerate() {
+            if let Piece::Piece(colour) = piece {
+                let fill = match colour {
+                    Colour::White => "#fff",
+                    Colour::Black => "#222",
+                    Colour::Red => "#c22",
+                };
+                svg += &format!(
+                    "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"9\" fill=\"{}\" stroke=\"#000\"/>\n",
+                    sx,
+                    sy + (height - 24.0) / 2.0 - 20.0 * slot as f64,
+                    fill
+                );
+            }
+        }
+    }
+    if !caption.is_empty() {
+        svg += &format!(
+            "  <text x=\"{}\" y=\"{}\" font-size=\"14\" text-anchor=\"middle\">{}</text>\n",
+            SVG_BOARD_WIDTH / 2,
+            SVG_BOARD_HEIGHT - 10,
+            caption
+        );
+    }
+    svg + "</g>\n"
+}
+
+fn svg_document(width: u32, height: u32, content: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n<rect width=\"{w}\" height=\"{h}\" fill=\"#fff\"/>\n{content}</svg>\n",
+        w = width,
+        h = height,
+        content = content
+    )
+}
+
+// Export a single position
+pub fn svg_board(board: &Board, highlight: &Highlight) -> String {
+    svg_document(
+        SVG_BOARD_WIDTH,
+        SVG_BOARD_HEIGHT,
+        &svg_board_group(board, highlight, 0, 0, ""),
+    )
+}
+
+// Export a whole game as the position after every round
+pub fn svg_game(rounds: &[Round], config: BoardConfig) -> String {
+    let mut board = Board::with_config(config);
+    let mut content = String::new();
+    for (i, round) in rounds.iter().enumerate() {
+        board.do_turn(round.played());
+        let (column, row) = (i as u32 % SVG_BOARDS_PER_ROW, i as u32 / SVG_BOARDS_PER_ROW);
+        let caption = format!(
+            "{}. {:?} {}",
+            i + 1,
+            round.proposed.colour(),
+            notation::round_to_string(round)
+        );
+        content += &svg_board_group(
+            &board,
+            &Highlight::for_board(&board, Some(round.played())),
+            column * SVG_BOARD_WIDTH,
+            row * SVG_BOARD_HEIGHT,
+            &caption,
+        );
+    }
+    let rows = (rounds.len() as u32).div_ceil(SVG_BOARDS_PER_ROW).max(1);
+    svg_document(
+        SVG_BOARD_WIDTH * SVG_BOARDS_PER_ROW.min(rounds.len().max(1) as u32),
+        SVG_BOARD_HEIGHT * rows,
+        &content,
+    )
+}
@@ -0,0 +1,172 @@
+use crate::game::*;
+
+// What happens when a turn completes winning patterns for both colours
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum SimultaneousWin {
+    Draw,
+    // The colour making the turn wins
+    MoverWins,
+    // The colour making the turn loses, completing the opponent's pattern is a mistake
+    MoverLoses,
+}
+
+// The rule interpretations a game is played with, the default is the standard game
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct RuleSet {
+    // The dimensions of the board the game is played on
+    pub board: BoardConfig,
+    pub simultaneous_win: SimultaneousWin,
+    // Distances around the ring a piece may move, counted the shorter way round,
+    // None for the neighbouring and opposite stacks of the standard game
+    pub move_distances: Option<Vec<usize>>,
+    // Whether the turn vetoed by second best may be played again
+    pub replay_vetoed: bool,
+    // How often each player may call second best in a game, unlimited if None
+    pub second_best_calls: Option<usize>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            board: BoardConfig::STANDARD,
+            simultaneous_win: SimultaneousWin::Draw,
+            move_distances: None,
+            replay_vetoed: false,
+            second_best_calls: None,
+        }
+    }
+}
+
+impl RuleSet {
+    // Change a single rule by name, as used by the `rules` command of the engine protocol:
+    //   variant standard|three, stacks <n>, height <n>, line <n>, pieces <n>, players <n>,
+    //   simultaneous draw|mover|loser,
+    //   distances <d>,<d>...|standard, replayvetoed yes|no, calls <n>|unlimited
+    // The board dimensions are not checked here, see `BoardConfig::is_valid`
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let number = || value.parse::<usize>().map_err(|_| format!("invalid {} {}", name, value));
+        match (name, value) {
+            ("variant", "standard") => self.board = BoardConfig::STANDARD,
+            ("variant", "three") => self.board = BoardConfig::THREE_PLAYER,
+            ("stacks", _) => self.board.stacks = number()?,
+            ("height", _) => self.board.height = number()?,
+            ("line", _) => self.board.line = number()?,
+            ("pieces", _) => self.board.pieces = number()?,
+            ("players", _) => self.board.players = number()?,
+            ("simultaneous", "draw") => self.simultaneous_win = SimultaneousWin::Draw,
+            ("simultaneous", "mover") => self.simultaneous_win = SimultaneousWin::MoverWins,
+            ("simultaneous", "loser") => self.simultaneous_win = SimultaneousWin::MoverLoses,
+            ("distances", "standard") => self.move_distances = None,
+            ("distances", value) => {
+                let distances = value
+                    .split(',')
+                    .map(|d| d.parse::<usize>().ok().filter(|d| *d >= 1))
+                    .collect::<Option<Vec<usize>>>()
+                    .ok_or_else(|| format!("invalid distances {}", value))?;
+                self.move_distances = Some(distances);
+            }
+            ("replayvetoed", "yes") => self.replay_vetoed = true,
+            ("replayvetoed", "no") => self.replay_vetoed = false,
+            ("calls", "unlimited") => self.second_best_calls = None,
+            ("calls", _) => self.second_best_calls = Some(number()?),
+            _ => return Err(format!("invalid rule {} {}", name, value)),
+        }
+        Ok(())
+    }
+
+    // Every rule as the name and value `set` reads, e.g. to send the rules to another instance
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let simultaneous = match self.simultaneous_win {
+            SimultaneousWin::Draw => "draw",
+            SimultaneousWin::MoverWins => "mover",
+            SimultaneousWin::MoverLoses => "loser",
+        };
+        let distances = match &self.move_distances {
+            Some(distances) => distances.iter().map(usize::to_string).collect::<Vec<String>>().join(","),
+            None => "standard".to_owned(),
+        };
+        vec![
+            ("stacks", self.board.stacks.to_string()),
+            ("height", self.board.height.to_string()),
+            ("line", self.board.line.to_string()),
+            ("pieces", self.board.pieces.to_string()),
+            ("players", self.board.players.to_string()),
+            ("simultaneous", simultaneous.to_owned()),
+            ("distances", distances),
+            ("replayvetoed", if self.replay_vetoed { "yes" } else { "no" }.to_owned()),
+            ("calls", self.second_best_calls.map_or("unlimited".to_owned(), |calls| calls.to_string())),
+        ]
+    }
+
+    // Every rule as "<name> <value>..." on a line, as the `rules` command of the engine protocol reads it
+    pub fn settings_line(&self) -> String {
+        self.settings()
+            .iter()
+            .map(|(name, value)| format!("{} {}", name, value))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    pub fn is_possible_turn(&self, board: &Board, turn: &Turn) -> bool {
+        match (turn, &self.move_distances) {
+            (Turn::Move(_, idx1, idx2), Some(distances)) => {
+                board.fits_stacks(turn) && distances.contains(&board.ring_distance(*idx1, *idx2))
+            }
+            _ => board.is_possible_turn(turn),
+        }
+    }
+
+    // Return all turns a colour can make, placing or moving depending on the phase of the game
+    pub fn possible_turns(&self, board: &Board, colour: Colour) -> Vec<Turn> {
+        let stacks = board.places.len();
+        let mut turns: Vec<Turn> = vec![];
+        if board.is_placing() {
+            for i in 0..stacks {
+                let turn = Turn::Place(colour, i);
+                if self.is_possible_turn(board, &turn) {
+                    turns.push(turn);
+                }
+            }
+        } else {
+            for i in 0..stacks {
+                for j in 1..stacks {
+                    let turn = Turn::Move(colour, i, (i + j) % stacks);
+                    if self.is_possible_turn(board, &turn) {
+                        turns.push(turn);
+                    }
+                }
+            }
+        }
+        turns
+    }
+
+    // With three players the policy only decides between the mover and a single other winner
+    fn simultaneous_result(&self, winners: &[Colour], mover: Colour) -> EndState {
+        let others: Vec<Colour> = winners.iter().copied().filter(|colour| *colour != mover).collect();
+        match (self.simultaneous_win, others.as_slice()) {
+            (SimultaneousWin::MoverWins, _) if winners.contains(&mover) => EndState::Win(mover),
+            (SimultaneousWin::MoverLoses, [other]) => EndState::Win(*other),
+            _ => EndState::Draw,
+        }
+    }
+
+    // Return the result of the board after a turn by the mover
+    pub fn is_won(&self, board: &Board, mover: Colour) -> Option<EndState> {
+        match board.winners().as_slice() {
+            [] => None,
+            [colour] => Some(EndState::Win(*colour)),
+            winners => Some(self.simultaneous_result(winners, mover)),
+        }
+    }
+
+    // Return how the board ends the game after a turn by the mover
+    pub fn outcome(&self, board: &Board, mover: Colour) -> Option<Outcome> {
+        let mut outcome = board.outcome()?;
+        if outcome.reason == EndReason::Simultaneous {
+            outcome.result = self.simultaneous_result(&board.winners(), mover);
+        }
+        Some(outcome)
+    }
+}
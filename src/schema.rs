@@ -0,0 +1,219 @@
+// The JSON form of the game types with the `serde` feature, so records, messages and tools share one format.
+// Stacks are counted from 0. With the standard rules the types look like this:
+//
+//   colour   "white" | "black" | "red"
+//   piece    colour, or null for a blank
+//   place    [piece, ...] from the bottom up, as many as the height of the stacks
+//   config   {"stacks": 8, "height": 3, "line": 4, "pieces": 8, "players": 2}
+//   board    {"places": [place, ...], "config": config}
+//   turn     {"type": "place", "colour": colour, "to": 2}
+//            {"type": "move", "colour": colour, "from": 2, "to": 6}
+//   round    {"proposed": turn, "second_turn": turn | null}
+//   result   {"win": colour} | "draw"
+//   outcome  {"result": result, "reason": "stack" | "ring_line" | "simultaneous" | "repetition"
+//             | "resignation" | "timeout" | "no_legal_move" | "forfeit", "stacks": [0, 1, 2, 3]}
+//   phase    "propose" | {"second_best": turn} | {"retry": turn} | {"over": outcome}
+//   rules    {"board": config, "simultaneous_win": "draw" | "mover_wins" | "mover_loses",
+//             "move_distances": [1, 4] | null, "replay_vetoed": false, "second_best_calls": 3 | null}
+//   game     {"rules": rules, "rounds": [round, ...], "callers": [colour | null, ...], "passes": 0,
+//             "phase": phase, "board": board, "seed": 42}
+//
+// Fields left out of rules and configs take their standard value.
+// A game is read by replaying its rounds, so only games played by the rules are accepted. Callers,
+// passes, phase, board and seed may be left out of a game, the board is only checked against the rounds.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{game::*, rules::RuleSet};
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TurnData {
+    Place { colour: Colour, to: usize },
+    Move { colour: Colour, from: usize, to: usize },
+}
+
+impl From<Turn> for TurnData {
+    fn from(turn: Turn) -> Self {
+        match turn {
+            Turn::Place(colour, to) => TurnData::Place { colour, to },
+            Turn::Move(colour, from, to) => TurnData::Move { colour, from, to },
+        }
+    }
+}
+
+impl From<TurnData> for Turn {
+    fn from(data: TurnData) -> Self {
+        match data {
+            TurnData::Place { colour, to } => Turn::Place(colour, to),
+            TurnData::Move { colour, from, to } => Turn::Move(colour, from, to),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BoardData {
+    places: Vec<Place>,
+    config: BoardConfig,
+}
+
+// A board has to fit its config, with the pieces of every stack at the bottom
+impl TryFrom<BoardData> for Board {
+    type Error = String;
+
+    fn try_from(data: BoardData) -> Result<Self, Self::Error> {
+        let config = data.config;
+        if !config.is_valid() {
+            return Err("invalid board config".to_string());
+        }
+        if data.places.len() != config.stacks {
+            return Err(format!("expected {} places", config.stacks));
+        }
+        let mut board = Board::with_config(config);
+        for (i, (place, stack)) in board.places.iter_mut().zip(data.places).enumerate() {
+            if stack.0.len() != config.height {
+                return Err(format!("place {} does not hold {} pieces", i, config.height));
+            }
+            let pieces = stack.0.iter().take_while(|piece| **piece != Piece::Blank);
+            for piece in pieces {
+                if let Piece::Piece(colour) = piece {
+                    place.add_piece(colour);
+                }
+            }
+            if *place != stack {
+                return Err(format!("place {} has a piece above a blank", i));
+            }
+        }
+        Ok(board)
+    }
+}
+
+#[derive(Serialize)]
+struct GameRef<'a> {
+    rules: &'a RuleSet,
+    rounds: &'a [Round],
+    callers: &'a [Option<Colour>],
+    passes: usize,
+    phase: &'a Phase,
+    board: &'a Board,
+    seed: u64,
+}
+
+#[derive(Deserialize)]
+struct GameData {
+    rules: RuleSet,
+    rounds: Vec<Round>,
+    #[serde(default)]
+    callers: Option<Vec<Option<Colour>>>,
+    #[serde(default)]
+    passes: usize,
+    #[serde(default)]
+    phase: Option<Phase>,
+    #[serde(default)]
+    board: Option<Board>,
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GameRef {
+            rules: &self.rules,
+            rounds: &self.rounds,
+            callers: self.callers(),
+            passes: self.passes(),
+            phase: &self.phase,
+            board: &self.board,
+            seed: self.seed,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GameData::deserialize(deserializer)?;
+        game_from_data(data).map_err(de::Error::custom)
+    }
+}
+
+// Replay the rounds with the recorded callers of second best, then bring the game into the recorded phase
+fn game_from_data(data: GameData) -> Result<Game, String> {
+    if !data.rules.board.is_valid() {
+        return Err("invalid board config".to_string());
+    }
+    if data.callers.as_ref().is_some_and(|callers| callers.len() != data.rounds.len()) {
+        return Err("expected a caller for every round".to_string());
+    }
+    let mut game = Game::with_rules(data.rules);
+    if let Some(seed) = data.seed {
+        game.seed = seed;
+    }
+    for (i, round) in data.rounds.iter().enumerate() {
+        replay_round(&mut game, round, data.callers.as_ref().map(|callers| callers[i]))
+            .map_err(|error| format!("round {}: {}", i + 1, error))?;
+    }
+
+    if let Some(phase) = data.phase {
+        let pending = match &phase {
+            Phase::Propose => Ok(()),
+            Phase::SecondBest(turn) => replay_pending(&mut game, turn, data.passes, false),
+            Phase::Retry(turn) => replay_pending(&mut game, turn, data.passes, true),
+            // Games ended early, e.g. by resignation, end without another turn
+            Phase::Over(outcome) if !matches!(game.phase, Phase::Over(_)) => {
+                let loser = early_loser(&game, outcome).ok_or_else(|| "phase: the game is not over on the board".to_string())?;
+                game.end(loser, outcome.reason)
+            }
+            Phase::Over(_) => Ok(()),
+        };
+        pending.map_err(|error| format!("phase: {}", error))?;
+        if game.phase != phase {
+            return Err("the phase does not match the rounds".to_string());
+        }
+    }
+    if data.board.is_some_and(|board| board != game.board) {
+        return Err("the board does not match the rounds".to_string());
+    }
+    Ok(game)
+}
+
+// Only resignations, timeouts and forfeits end a game before the board does. The colour that ended it is
+// the opponent of the winner, with more players nobody wins and any colour gives the same outcome
+fn early_loser(game: &Game, outcome: &Outcome) -> Option<Colour> {
+    if !matches!(outcome.reason, EndReason::Resignation | EndReason::Timeout | EndReason::Forfeit) {
+        return None;
+    }
+    match (game.players(), &outcome.result) {
+        (2, EndState::Win(winner)) if *winner != Colour::Red => Some(winner.opposite()),
+        (2, _) => None,
+        (_, EndState::Draw) => Some(game.to_move()),
+        (_, EndState::Win(_)) => None,
+    }
+}
+
+// Without the callers the first opponent to decide is taken to have called second best, as in records
+fn replay_round(game: &mut Game, round: &Round, caller: Option<Option<Colour>>) -> Result<(), GameError> {
+    game.propose_turn(round.proposed.clone())?;
+    while let (Phase::SecondBest(_), Some(colour)) = (&game.phase, game.waiting_for()) {
+        let call = match caller {
+            Some(caller) => caller == Some(colour),
+            None => round.second_turn.is_some(),
+        };
+        game.decide_second_best(colour, call)?;
+    }
+    if let Some(second_turn) = &round.second_turn {
+        game.retry_turn(second_turn.clone())?;
+    }
+    Ok(())
+}
+
+// Propose the turn of the unfinished round and let the recorded number of opponents pass,
+// the next one calls second best if it was called
+fn replay_pending(game: &mut Game, turn: &Turn, passes: usize, call: bool) -> Result<(), GameError> {
+    game.propose_turn(turn.clone())?;
+    for decision in (0..passes).map(|_| false).chain(call.then_some(true)) {
+        let colour = game.waiting_for().ok_or(GameError::GameOver)?;
+        game.decide_second_best(colour, decision)?;
+    }
+    Ok(())
+}
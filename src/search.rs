@@ -0,0 +1,169 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Mutex,
+};
+
+use crate::game::*;
+
+// Entries kept before the table is cleared, about 64 MB
+const TABLE_ENTRIES: usize = 1 << 20;
+
+// How a stored score relates to the real score of a position
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Bound {
+    Exact,
+    // The real score is at least the stored one, the search was cut off
+    Lower,
+    // The real score is at most the stored one, no turn reached alpha
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub depth: u64,
+    pub score: i32,
+    pub bound: Bound,
+    pub best: Option<Turn>,
+}
+
+// Positions searched before with their score, shared by all threads searching for a bot and kept
+// between the iterations of a deepening search
+#[derive(Default)]
+pub struct TranspositionTable {
+    entries: Mutex<HashMap<u64, Entry>>,
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The key of a board with the colour to move on it
+    pub fn key(board: &Board, colour: Colour) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        board.hash(&mut hasher);
+        colour.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries.lock().unwrap().get(&key).cloned()
+    }
+
+    // Deeper searches of a position replace shallower ones
+    pub fn store(&self, key: u64, entry: Entry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= TABLE_ENTRIES {
+            entries.clear();
+        }
+        if entries.get(&key).is_none_or(|old| old.depth <= entry.depth) {
+            entries.insert(key, entry);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Counters of the work done by a search, to see how well turns are ordered
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    // Positions searched
+    pub nodes: u64,
+    // Positions searched beyond the depth because of threats to win
+    pub quiescence_nodes: u64,
+    // Positions whose score was taken from the transposition table
+    pub table_hits: u64,
+    // Positions where a turn was good enough to skip the remaining turns
+    pub cutoffs: u64,
+    // Cutoffs by the first turn searched, the better the ordering the closer to all cutoffs
+    pub first_cutoffs: u64,
+    // Searches repeated with a wider window, after a null window or aspiration window failed
+    pub researches: u64,
+}
+
+impl SearchStats {
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.quiescence_nodes += other.quiescence_nodes;
+        self.table_hits += other.table_hits;
+        self.cutoffs += other.cutoffs;
+        self.first_cutoffs += other.first_cutoffs;
+        self.researches += other.researches;
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let first = if self.cutoffs == 0 {
+            0.0
+        } else {
+            100.0 * self.first_cutoffs as f64 / self.cutoffs as f64
+        };
+        write!(
+            f,
+            "nodes {} quiescencenodes {} tablehits {} cutoffs {} firstcutoffs {:.1}% researches {}",
+            self.nodes, self.quiescence_nodes, self.table_hits, self.cutoffs, first, self.researches
+        )
+    }
+}
+
+// What a searching thread learns about good turns while searching: killer turns caused
+// cutoffs at the same distance from the root, the history counts cutoffs of a turn anywhere
+pub struct SearchState {
+    pub stats: SearchStats,
+    killers: Vec<[Option<Turn>; 2]>,
+    history: Vec<u64>,
+    stacks: usize,
+}
+
+impl SearchState {
+    pub fn new(config: BoardConfig) -> Self {
+        Self {
+            stats: SearchStats::default(),
+            killers: Vec::new(),
+            history: vec![0; config.stacks * config.stacks],
+            stacks: config.stacks,
+        }
+    }
+
+    fn history_index(&self, turn: &Turn) -> usize {
+        match *turn {
+            Turn::Place(_, idx) => idx,
+            Turn::Move(_, idx1, idx2) => idx1 * self.stacks + idx2,
+        }
+    }
+
+    pub fn is_killer(&self, ply: usize, turn: &Turn) -> bool {
+        self.killers.get(ply).is_some_and(|killers| killers.contains(&Some(turn.clone())))
+    }
+
+    pub fn history(&self, turn: &Turn) -> u64 {
+        self.history[self.history_index(turn)]
+    }
+
+    // Remember a turn that caused a cutoff, deeper searches count more
+    pub fn add_cutoff(&mut self, ply: usize, turn: &Turn, depth: u64) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(turn) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(turn.clone());
+        }
+        let idx = self.history_index(turn);
+        self.history[idx] += depth * depth;
+    }
+}
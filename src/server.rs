@@ -0,0 +1,469 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{bot::Bot, game::*, notation, rules::RuleSet};
+
+// A server hosting many games at once, clients talk to it with one command per line.
+//
+// Client to server:
+//   list                                   list the tables, answered with table lines and "end"
+//   create [w|b] [bot [<depth>]]           open a table and sit down, optionally against a bot of depth at most 6
+//   join <table> <w|b|spectator>           sit down at a table or watch it
+//   leave                                  leave the table, resigning a running game
+//   propose <turn>                         propose a turn for your colour
+//   secondbest yes|no                      decide on the turn proposed by your opponent
+//   retry <turn>                           play another turn after second best was called
+//   resign
+//   quit
+//
+// Server to client:
+//   welcome <version>
+//   table <table> <white seat> <black seat> <waiting|playing|over>   seats are open, taken or bot
+//   joined <table> <w|b|spectator>
+//   left <table>
+//   state <table> <board> <to move> propose|secondbest <turn>|retry <turn>|over <w|b|draw> <reason> [stacks]
+//   round <table> <round>                  a round was played
+//   error <message>
+
+pub const SERVER_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7979;
+
+// Search depth of server-side bots when the client does not choose one
+const DEFAULT_BOT_DEPTH: u64 = 3;
+
+// Deepest search a client may ask of a server-side bot, the same cap as the HTTP server's
+pub const MAX_BOT_DEPTH: u64 = 6;
+
+// Pause after a failed accept before waiting for the next client
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Seat {
+    Open,
+    Client(usize),
+    Bot(u64),
+}
+
+struct Table {
+    game: Game,
+    seats: [Seat; 2],
+    spectators: Vec<usize>,
+}
+
+impl Table {
+    fn seat(&self, colour: Colour) -> Seat {
+        self.seats[colour_index(colour)]
+    }
+
+    fn is_started(&self) -> bool {
+        !self.seats.contains(&Seat::Open)
+    }
+
+    fn members(&self) -> Vec<usize> {
+        let mut members = self.spectators.clone();
+        for seat in self.seats {
+            if let Seat::Client(client) = seat {
+                members.push(client);
+            }
+        }
+        members
+    }
+
+    fn status(&self) -> &'static str {
+        match (&self.game.phase, self.is_started()) {
+            (Phase::Over(_), _) => "over",
+            (_, true) => "playing",
+            (_, false) => "waiting",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Lobby {
+    // The rules every table plays with
+    rules: RuleSet,
+    tables: BTreeMap<usize, Table>,
+    clients: HashMap<usize, Sender<String>>,
+    // The table each client is at and the colour it plays there, None for spectators
+    memberships: HashMap<usize, (usize, Option<Colour>)>,
+    next_table: usize,
+    next_client: usize,
+}
+
+impl Lobby {
+    fn send(&self, client: usize, line: String) {
+        if let Some(sender) = self.clients.get(&client) {
+            let _ = sender.send(line);
+        }
+    }
+
+    fn broadcast(&self, table_id: usize, line: String) {
+        if let Some(table) = self.tables.get(&table_id) {
+            for client in table.members() {
+                self.send(client, line.clone());
+            }
+        }
+    }
+
+    fn broadcast_state(&self, table_id: usize) {
+        if let Some(table) = self.tables.get(&table_id) {
+            self.broadcast(table_id, state_line(table_id, &table.game));
+        }
+    }
+
+    fn list(&self, client: usize) {
+        let seat_string = |seat: Seat| match seat {
+            Seat::Open => "open",
+            Seat::Client(_) => "taken",
+            Seat::Bot(_) => "bot",
+        };
+        for (id, table) in &self.tables {
+            self.send(
+                client,
+                format!(
+                    "table {} {} {} {}",
+                    id,
+                    seat_string(table.seat(Colour::White)),
+                    seat_string(table.seat(Colour::Black)),
+                    table.status()
+                ),
+            );
+        }
+        self.send(client, "end".to_owned());
+    }
+
+    fn create(&mut self, client: usize, colour: Colour, bot: Option<u64>) -> Result<usize, String> {
+        self.leave(client);
+        let id = self.next_table;
+        self.next_table += 1;
+        let mut seats = [Seat::Open; 2];
+        seats[colour_index(colour)] = Seat::Client(client);
+        if let Some(depth) = bot {
+            seats[colour_index(colour.opposite())] = Seat::Bot(depth);
+        }
+        self.tables.insert(
+            id,
+            Table {
+                game: Game::with_rules(self.rules.clone()),
+                seats,
+                spectators: Vec::new(),
+            },
+        );
+        self.memberships.insert(client, (id, Some(colour)));
+        self.send(client, format!("joined {} {}", id, notation::colour_to_char(colour)));
+        self.broadcast_state(id);
+        Ok(id)
+    }
+
+    fn join(&mut self, client: usize, id: usize, colour: Option<Colour>) -> Result<usize, String> {
+        let table = self.tables.get(&id).ok_or_else(|| format!("no table {}", id))?;
+        if let Some(colour) = colour {
+            if table.seat(colour) != Seat::Open {
+                return Err(format!("seat {} is taken", notation::colour_to_char(colour)));
+            }
+        }
+        self.leave(client);
+        let table = self.tables.get_mut(&id).ok_or_else(|| format!("no table {}", id))?;
+        match colour {
+            Some(colour) => table.seats[colour_index(colour)] = Seat::Client(client),
+            None => table.spectators.push(client),
+        }
+        self.memberships.insert(client, (id, colour));
+        let seat = colour.map_or("spectator".to_owned(), |colour| notation::colour_to_char(colour).to_string());
+        self.send(client, format!("joined {} {}", id, seat));
+        self.broadcast_state(id);
+        Ok(id)
+    }
+
+    // Leave the current table, a running game is resigned and empty tables are closed
+    fn leave(&mut self, client: usize) {
+        let Some((id, colour)) = self.memberships.remove(&client) else {
+            return;
+        };
+        let Some(table) = self.tables.get_mut(&id) else {
+            return;
+        };
+        match colour {
+            Some(colour) => {
+                if table.is_started() && table.game.resign(colour).is_ok() {
+                    self.broadcast_state(id);
+                }
+                if let Some(table) = self.tables.get_mut(&id) {
+                    table.seats[colour_index(colour)] = Seat::Open;
+                }
+            }
+            None => table.spectators.retain(|spectator| *spectator != client),
+        }
+        self.send(client, format!("left {}", id));
+        if self.tables.get(&id).is_some_and(|table| table.members().is_empty()) {
+            self.tables.remove(&id);
+        }
+    }
+
+    // Apply an action of a client playing at a table
+    fn play(&mut self, client: usize, action: Action) -> Result<usize, String> {
+        let (id, colour) = match self.memberships.get(&client) {
+            Some((id, Some(colour))) => (*id, *colour),
+            Some((_, None)) => return Err("spectators cannot play".to_owned()),
+            None => return Err("not at a table".to_owned()),
+        };
+        let table = self.tables.get_mut(&id).ok_or_else(|| format!("no table {}", id))?;
+        if !table.is_started() {
+            return Err("waiting for an opponent".to_owned());
+        }
+        let rounds = table.game.rounds.len();
+        apply_action(&mut table.game, colour, action).map_err(|error| error.to_string())?;
+        self.after_action(id, rounds);
+        Ok(id)
+    }
+
+    // Tell everyone at the table what happened, including the round if one was completed
+    fn after_action(&self, id: usize, rounds_before: usize) {
+        if let Some(table) = self.tables.get(&id) {
+            if let Some(round) = table.game.rounds.get(rounds_before) {
+                self.broadcast(id, format!("round {} {}", id, notation::round_to_string(round)));
+            }
+        }
+        self.broadcast_state(id);
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Action {
+    Propose(Turn),
+    SecondBest(bool),
+    Retry(Turn),
+    Resign,
+}
+
+fn apply_action(game: &mut Game, colour: Colour, action: Action) -> Result<(), GameError> {
+    match action {
+        Action::Propose(turn) => game.propose_turn(turn),
+        Action::SecondBest(call) => game.decide_second_best(colour, call),
+        Action::Retry(turn) => game.retry_turn(turn),
+        Action::Resign => game.resign(colour),
+    }
+}
+
+// Tables seat two players, commands with other colours are rejected when they are parsed
+// and `serve` refuses rules for more players
+fn colour_index(colour: Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+        Colour::Red => panic!("tables have no seat for Red"),
+    }
+}
+
+fn state_line(id: usize, game: &Game) -> String {
+    let phase = match &game.phase {
+        Phase::Propose => "propose".to_owned(),
+        Phase::SecondBest(turn) => format!("secondbest {}", notation::turn_to_string(turn)),
+        Phase::Retry(turn) => format!("retry {}", notation::turn_to_string(turn)),
+        Phase::Over(outcome) => format!("over {}", notation::outcome_to_string(outcome)),
+    };
+    format!(
+        "state {} {} {} {}",
+        id,
+        notation::board_to_string(&game.board),
+        notation::colour_to_char(game.to_move()),
+        phase
+    )
+}
+
+// Let server-side bots act at a table for as long as the game waits for them
+fn run_bots(lobby: &Mutex<Lobby>, id: usize) {
+    loop {
+        // Search without holding the lock so other tables keep going
+        let job = {
+            let lobby = lobby.lock().unwrap();
+            let Some(table) = lobby.tables.get(&id) else {
+                return;
+            };
+            let Some(colour) = table.game.waiting_for() else {
+                return;
+            };
+            match table.seat(colour) {
+                Seat::Bot(depth) if table.is_started() => (
+                    colour,
+                    Bot::new(colour, depth)
+                        .with_rules(table.game.rules.clone())
+                        .with_seed(table.game.bot_seed(colour)),
+                    table.game.board.clone(),
+                    table.game.phase.clone(),
+                    table.game.rounds.len(),
+                    table.game.can_call_second_best(),
+                ),
+                _ => return,
+            }
+        };
+        let (colour, bot, board, phase, rounds, can_call) = job;
+        let action = match &phase {
+            Phase::Propose => Action::Propose(bot.choose_turn(&board, None)),
+            Phase::SecondBest(turn) => Action::SecondBest(can_call && bot.would_call_second_best(&board, turn)),
+            Phase::Retry(vetoed) => Action::Retry(bot.choose_turn(&board, Some(vetoed))),
+            Phase::Over(_) => return,
+        };
+
+        let mut lobby = lobby.lock().unwrap();
+        let Some(table) = lobby.tables.get_mut(&id) else {
+            return;
+        };
+        // Another thread may have acted for the bot in the meantime
+        if table.game.phase != phase || table.game.rounds.len() != rounds {
+            continue;
+        }
+        if apply_action(&mut table.game, colour, action).is_err() {
+            return;
+        }
+        lobby.after_action(id, rounds);
+    }
+}
+
+fn handle_command(lobby: &Arc<Mutex<Lobby>>, client: usize, line: &str) -> bool {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let mut guard = lobby.lock().unwrap();
+    let colour = guard.memberships.get(&client).and_then(|(_, colour)| *colour);
+    let parse_turn = |turn: &str| {
+        let colour = colour.ok_or_else(|| "not playing at a table".to_owned())?;
+        notation::parse_turn(turn, colour).ok_or_else(|| format!("invalid turn {}", turn))
+    };
+    let result = match tokens.as_slice() {
+        ["list"] => {
+            guard.list(client);
+            Ok(None)
+        }
+        ["create", options @ ..] => parse_create(options)
+            .and_then(|(colour, bot)| guard.create(client, colour, bot))
+            .map(Some),
+        ["join", id, seat] => match (id.parse::<usize>(), *seat) {
+            (Ok(id), "spectator") => guard.join(client, id, None).map(Some),
+            (Ok(id), seat) => match seat.chars().next().and_then(notation::parse_colour) {
+                Some(colour) if seat.len() == 1 && Colour::playing(2).contains(&colour) => {
+                    guard.join(client, id, Some(colour)).map(Some)
+                }
+                _ => Err(format!("invalid seat {}", seat)),
+            },
+            (Err(_), _) => Err(format!("invalid table {}", id)),
+        },
+        ["leave"] => {
+            guard.leave(client);
+            Ok(None)
+        }
+        ["propose", turn] => parse_turn(turn).and_then(|turn| guard.play(client, Action::Propose(turn))).map(Some),
+        ["secondbest", "yes"] => guard.play(client, Action::SecondBest(true)).map(Some),
+        ["secondbest", "no"] => guard.play(client, Action::SecondBest(false)).map(Some),
+        ["retry", turn] => parse_turn(turn).and_then(|turn| guard.play(client, Action::Retry(turn))).map(Some),
+        ["resign"] => guard.play(client, Action::Resign).map(Some),
+        ["quit"] => return false,
+        [] => Ok(None),
+        _ => Err(format!("unknown command {}", line.trim())),
+    };
+    match result {
+        Ok(Some(id)) => {
+            drop(guard);
+            let lobby = lobby.clone();
+            thread::spawn(move || run_bots(&lobby, id));
+        }
+        Ok(None) => {}
+        Err(error) => guard.send(client, format!("error {}", error)),
+    }
+    true
+}
+
+fn parse_create(options: &[&str]) -> Result<(Colour, Option<u64>), String> {
+    let (colour, options) = match options.first().and_then(|c| c.chars().next()).and_then(notation::parse_colour) {
+        Some(colour) if options[0].len() == 1 && Colour::playing(2).contains(&colour) => (colour, &options[1..]),
+        _ => (Colour::White, options),
+    };
+    match options {
+        [] => Ok((colour, None)),
+        ["bot"] => Ok((colour, Some(DEFAULT_BOT_DEPTH))),
+        ["bot", depth] => depth
+            .parse::<u64>()
+            .map_err(|_| format!("invalid depth {}", depth))
+            .and_then(|depth| {
+                if depth > MAX_BOT_DEPTH {
+                    Err(format!("bots search to a depth of at most {}", MAX_BOT_DEPTH))
+                } else {
+                    Ok((colour, Some(depth)))
+                }
+            }),
+        _ => Err("expected create [w|b] [bot [<depth>]]".to_owned()),
+    }
+}
+
+fn handle_client(lobby: Arc<Mutex<Lobby>>, stream: TcpStream) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let client = {
+        let mut lobby = lobby.lock().unwrap();
+        let client = lobby.next_client;
+        lobby.next_client += 1;
+        lobby.clients.insert(client, sender);
+        client
+    };
+
+    // Write on a separate thread so a slow client does not hold up the lobby
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        for line in receiver {
+            if writeln!(writer, "{}", line).and_then(|_| writer.flush()).is_err() {
+                return;
+            }
+        }
+    });
+
+    lobby.lock().unwrap().send(client, format!("welcome {}", SERVER_VERSION));
+    for line in BufReader::new(stream).lines() {
+        match line {
+            Ok(line) => {
+                if !handle_command(&lobby, client, &line) {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    let mut lobby = lobby.lock().unwrap();
+    lobby.leave(client);
+    lobby.clients.remove(&client);
+    Ok(())
+}
+
+// Accept clients on a listener until the server is killed, a client that fails to connect
+// does not affect the others. Tables seat two players, other rules are refused
+pub fn serve(listener: TcpListener, rules: RuleSet) -> io::Result<()> {
+    if rules.board.players != 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the server is for two players"));
+    }
+    let lobby = Arc::new(Mutex::new(Lobby { rules, ..Lobby::default() }));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("could not accept a client: {}", error);
+                // Errors like running out of file descriptors last a while
+                thread::sleep(ACCEPT_RETRY);
+                continue;
+            }
+        };
+        let lobby = lobby.clone();
+        thread::spawn(move || handle_client(lobby, stream));
+    }
+    Ok(())
+}
+
+// Accept clients on the port of every interface
+pub fn run(port: u16, rules: RuleSet) -> io::Result<()> {
+    serve(TcpListener::bind(("0.0.0.0", port))?, rules)
+}
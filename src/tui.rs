@@ -0,0 +1,468 @@
+use std::io::{self, Stdout, Write};
+
+use crossterm::{
+    cursor,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEventKind},
+    execute, queue,
+    style::{Attribute, Color, Print, SetAttribute, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+
+use crate::{bot::Bot, game::*, io::IO, notation};
+
+const CALL_BUTTON: (u16, &str) = (2, "[ Second best! ]");
+const PASS_BUTTON: (u16, &str) = (20, "[ Let it pass ]");
+
+// Where things go on the screen for the board of a game
+struct Layout {
+    // Screen position of each stack
+    stacks: Vec<(u16, u16)>,
+    stack_width: u16,
+    history_column: u16,
+    status_row: u16,
+    button_row: u16,
+}
+
+impl Layout {
+    // The ring of `IO::print_board`: the first half of the stacks goes up the left side and the rest
+    // down the right side, the top and bottom rows are drawn closer together
+    fn new(config: BoardConfig) -> Self {
+        let stack_width = config.height as u16 + 2;
+        let (left_edge, left_inner) = (8, 4);
+        let right_edge = left_edge + stack_width + 7;
+        let right_inner = right_edge + 4;
+        let rows = config.stacks.div_ceil(2);
+        let stacks = (0..config.stacks)
+            .map(|idx| {
+                let (row, left) = if idx < rows { (rows - 1 - idx, true) } else { (idx - rows, false) };
+                let edge = row == 0 || row == rows - 1;
+                let x = match (left, edge) {
+                    (true, true) => left_edge,
+                    (true, false) => left_inner,
+                    (false, true) => right_edge,
+                    (false, false) => right_inner,
+                };
+                (x, 2 + 3 * row as u16)
+            })
+            .collect();
+        let status_row = 2 + 3 * rows as u16;
+        Self {
+            stacks,
+            stack_width,
+            history_column: right_inner + stack_width + 11,
+            status_row,
+            button_row: status_row + 2,
+        }
+    }
+
+    // The number of a stack goes outside the ring
+    fn label_x(&self, (x, _): (u16, u16)) -> u16 {
+        if x < self.stacks[0].0 + self.stack_width {
+            x - 3
+        } else {
+            x + self.stack_width + 2
+        }
+    }
+
+    fn stack_at(&self, x: u16, y: u16) -> Option<usize> {
+        self.stacks.iter().position(|&(sx, sy)| y == sy && x >= sx && x < sx + self.stack_width)
+    }
+}
+
+enum Controller {
+    Human,
+    Bot(Box<Bot>),
+}
+
+// What a keypress or click asks for
+enum Input {
+    Stack(usize),
+    // A digit of the number of a stack
+    Digit(usize),
+    Next,
+    Previous,
+    Confirm,
+    Cancel,
+    CallSecondBest(bool),
+    ToggleButton,
+    Quit,
+    None,
+}
+
+pub struct Tui {
+    game: Game,
+    // Who plays each colour taking part, in turn order
+    controllers: Vec<Controller>,
+    layout: Layout,
+    cursor: usize,
+    // The number typed so far while it may still be followed by another digit, 0 if none
+    typed: usize,
+    selected: Option<usize>,
+    call_focused: bool,
+    message: String,
+    stdout: Stdout,
+}
+
+impl Tui {
+    // Run a full-screen game, the terminal is restored afterwards even if drawing fails
+    pub fn run(game: Game) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, EnableMouseCapture, cursor::Hide)?;
+        let result = Self::choose_mode(&mut stdout, &game).and_then(|controllers| match controllers {
+            Some(controllers) => Tui {
+                layout: Layout::new(game.board.config),
+                game,
+                controllers,
+                cursor: 0,
+                typed: 0,
+                selected: None,
+                call_focused: false,
+                message: String::new(),
+                stdout,
+            }
+            .gameloop(),
+            None => Ok(()),
+        });
+        let mut stdout = io::stdout();
+        execute!(stdout, cursor::Show, DisableMouseCapture, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    // Play one colour against the bots or let people play every colour
+    fn choose_mode(stdout: &mut Stdout, game: &Game) -> io::Result<Option<Vec<Controller>>> {
+        let colours = Colour::playing(game.players());
+        let opponents = if colours.len() == 2 { "the bot" } else { "the bots" };
+        queue!(stdout, terminal::Clear(ClearType::All), cursor::MoveTo(2, 1), Print("Second Best"))?;
+        for (row, colour) in (3..).zip(colours) {
+            queue!(
+                stdout,
+                cursor::MoveTo(2, row),
+                Print(format!("{}  play against {} as {:?}", notation::colour_to_char(*colour), opponents, colour))
+            )?;
+        }
+        let row = 3 + colours.len() as u16;
+        queue!(
+            stdout,
+            cursor::MoveTo(2, row),
+            Print(if colours.len() == 2 { "p  play against another player" } else { "p  play against other players" }),
+            cursor::MoveTo(2, row + 1),
+            Print("q  quit"),
+        )?;
+        stdout.flush()?;
+        loop {
+            if let Event::Key(KeyEvent { code: KeyCode::Char(c), kind: KeyEventKind::Press, .. }) = event::read()? {
+                match c {
+                    'p' => return Ok(Some(colours.iter().map(|_| Controller::Human).collect())),
+                    'q' => return Ok(None),
+                    _ => {
+                        let Some(human) = notation::parse_colour(c).filter(|colour| colours.contains(colour)) else {
+                            continue;
+                        };
+                        let controllers = colours
+                            .iter()
+                            .map(|&colour| {
+                                if colour == human {
+                                    Controller::Human
+                                } else {
+                                    let bot = Bot::new(colour, 3).with_rules(game.rules.clone()).with_seed(game.bot_seed(colour));
+                                    Controller::Bot(Box::new(bot))
+                                }
+                            })
+                            .collect();
+                        return Ok(Some(controllers));
+                    }
+                }
+            }
+        }
+    }
+
+    fn controller(&self, colour: Colour) -> &Controller {
+        let idx = Colour::ALL.iter().position(|c| *c == colour).unwrap_or(0);
+        &self.controllers[idx]
+    }
+
+    fn gameloop(mut self) -> io::Result<()> {
+        loop {
+            self.draw()?;
+            let Some(colour) = self.game.waiting_for() else {
+                // Show the result until a key is pressed
+                loop {
+                    if let Event::Key(KeyEvent { kind: KeyEventKind::Press, .. }) = event::read()? {
+                        return Ok(());
+                    }
+                }
+            };
+            if let Controller::Bot(bot) = self.controller(colour) {
+                let result = match self.game.phase.clone() {
+                    Phase::Propose => self.game.propose_turn(bot.choose_turn(&self.game.board, None)),
+                    Phase::SecondBest(turn) => {
+                        let call = self.game.can_call_second_best() && bot.would_call_second_best(&self.game.board, &turn);
+                        self.message = if call { "Second best!".to_owned() } else { String::new() };
+                        self.game.decide_second_best(colour, call)
+                    }
+                    Phase::Retry(vetoed) => self.game.retry_turn(bot.choose_turn(&self.game.board, Some(&vetoed))),
+                    Phase::Over(_) => Ok(()),
+                };
+                if let Err(error) = result {
+                    self.message = error.to_string();
+                }
+                continue;
+            }
+            match self.read_input()? {
+                Input::Quit => return Ok(()),
+                input => self.handle_input(colour, input),
+            }
+        }
+    }
+
+    fn read_input(&self) -> io::Result<Input> {
+        Ok(match event::read()? {
+            Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) => match code {
+                KeyCode::Right | KeyCode::Up => Input::Next,
+                KeyCode::Left | KeyCode::Down => Input::Previous,
+                KeyCode::Enter | KeyCode::Char(' ') => Input::Confirm,
+                KeyCode::Esc => Input::Cancel,
+                KeyCode::Tab => Input::ToggleButton,
+                KeyCode::Char('s') => Input::CallSecondBest(true),
+                KeyCode::Char('n') => Input::CallSecondBest(false),
+                KeyCode::Char('q') => Input::Quit,
+                KeyCode::Char(c @ '0'..='9') => Input::Digit(c as usize - '0' as usize),
+                _ => Input::None,
+            },
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                let (x, y) = (mouse.column, mouse.row);
+                let row = self.layout.button_row;
+                let hit = |(bx, label): (u16, &str)| y == row && x >= bx && x < bx + label.chars().count() as u16;
+                if hit(CALL_BUTTON) {
+                    Input::CallSecondBest(true)
+                } else if hit(PASS_BUTTON) {
+                    Input::CallSecondBest(false)
+                } else {
+                    self.layout.stack_at(x, y).map_or(Input::None, Input::Stack)
+                }
+            }
+            _ => Input::None,
+        })
+    }
+
+    fn handle_input(&mut self, colour: Colour, input: Input) {
+        let stacks = self.game.board.places.len();
+        if !matches!(input, Input::Digit(_)) {
+            self.typed = 0;
+        }
+        match (&self.game.phase, input) {
+            (Phase::SecondBest(_), Input::ToggleButton | Input::Next | Input::Previous) => {
+                self.call_focused = !self.call_focused;
+            }
+            (Phase::SecondBest(_), Input::Confirm) => self.decide_second_best(colour, self.call_focused),
+            (Phase::SecondBest(_), Input::CallSecondBest(call)) => self.decide_second_best(colour, call),
+            (Phase::Propose | Phase::Retry(_), Input::Next) => self.cursor = (self.cursor + 1) % stacks,
+            (Phase::Propose | Phase::Retry(_), Input::Previous) => self.cursor = (self.cursor + stacks - 1) % stacks,
+            (Phase::Propose | Phase::Retry(_), Input::Digit(digit)) => self.type_digit(colour, digit),
+            (Phase::Propose | Phase::Retry(_), Input::Cancel) => self.selected = None,
+            (Phase::Propose | Phase::Retry(_), Input::Confirm) => self.select_stack(colour, self.cursor),
+            (Phase::Propose | Phase::Retry(_), Input::Stack(idx)) => {
+                self.cursor = idx;
+                self.select_stack(colour, idx);
+            }
+            _ => {}
+        }
+    }
+
+    // Stacks are chosen by their number, the cursor follows the digits typed. A number that may still
+    // be followed by another digit waits for it, or for enter to choose the stack under the cursor
+    fn type_digit(&mut self, colour: Colour, digit: usize) {
+        let stacks = self.game.board.places.len();
+        let number = self.typed * 10 + digit;
+        if !(1..=stacks).contains(&number) {
+            // The digit may start a new number instead
+            if self.typed != 0 {
+                self.typed = 0;
+                self.type_digit(colour, digit);
+            }
+            return;
+        }
+        self.cursor = number - 1;
+        if number * 10 > stacks {
+            self.typed = 0;
+            self.select_stack(colour, number - 1);
+        } else {
+            self.typed = number;
+        }
+    }
+
+    fn decide_second_best(&mut self, colour: Colour, call: bool) {
+        self.call_focused = false;
+        self.message = if call { "Second best! Try a new move.".to_owned() } else { String::new() };
+        if let Err(error) = self.game.decide_second_best(colour, call) {
+            self.message = error.to_string();
+        }
+    }
+
+    // Place on a stack, or pick the stack to move from and then the one to move to
+    fn select_stack(&mut self, colour: Colour, idx: usize) {
+        let turn = if self.game.board.is_placing() {
+            Turn::Place(colour, idx)
+        } else {
+            match self.selected {
+                None => {
+                    if self.destinations(colour, idx).is_empty() {
+                        self.message = "No piece to move there.".to_owned();
+                    } else {
+                        self.selected = Some(idx);
+                        self.message.clear();
+                    }
+                    return;
+                }
+                Some(from) if from == idx => {
+                    self.selected = None;
+                    return;
+                }
+                Some(from) => Turn::Move(colour, from, idx),
+            }
+        };
+        let result = match self.game.phase {
+            Phase::Retry(_) => self.game.retry_turn(turn),
+            _ => self.game.propose_turn(turn),
+        };
+        match result {
+            Ok(()) => {
+                self.selected = None;
+                self.message.clear();
+            }
+            Err(error) => self.message = error.to_string(),
+        }
+    }
+
+    // The stacks a piece on top of a stack can move to
+    fn destinations(&self, colour: Colour, from: usize) -> Vec<usize> {
+        (0..self.game.board.places.len())
+            .filter(|&to| self.game.rules.is_possible_turn(&self.game.board, &Turn::Move(colour, from, to)))
+            .collect()
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let board = match &self.game.phase {
+            // Show the proposed turn while the opponent decides on it
+            Phase::SecondBest(turn) => {
+                let mut board = self.game.board.clone();
+                board.do_turn(turn);
+                board
+            }
+            _ => self.game.board.clone(),
+        };
+        let to_move = self.game.to_move();
+        let destinations = match self.selected {
+            Some(from) => self.destinations(to_move, from),
+            None => Vec::new(),
+        };
+        let human_to_act = self
+            .game
+            .waiting_for()
+            .is_some_and(|colour| matches!(self.controller(colour), Controller::Human));
+
+        let winning_stacks = match &self.game.phase {
+            Phase::Over(outcome) => outcome.stacks.clone(),
+            _ => Vec::new(),
+        };
+
+        queue!(self.stdout, terminal::Clear(ClearType::All))?;
+        for (idx, &(x, y)) in self.layout.stacks.iter().enumerate() {
+            let label_x = self.layout.label_x((x, y));
+            queue!(self.stdout, cursor::MoveTo(label_x, y), Print(idx + 1), cursor::MoveTo(x, y))?;
+            if winning_stacks.contains(&idx) {
+                queue!(self.stdout, SetForegroundColor(Color::Green), SetAttribute(Attribute::Bold))?;
+            } else if self.selected == Some(idx) {
+                queue!(self.stdout, SetForegroundColor(Color::Yellow))?;
+            } else if destinations.contains(&idx) {
+                queue!(self.stdout, SetForegroundColor(Color::Green))?;
+            }
+            let playing = matches!(self.game.phase, Phase::Propose | Phase::Retry(_));
+            if idx == self.cursor && playing && human_to_act {
+                queue!(self.stdout, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                self.stdout,
+                Print(format!("[{}]", IO::place_string(&board.places[idx]))),
+                SetAttribute(Attribute::Reset),
+                SetForegroundColor(Color::Reset)
+            )?;
+        }
+
+        // Reserves and history next to the board
+        let on_board = |colour: Colour| {
+            board
+                .places
+                .iter()
+                .flat_map(|place| place.0.iter())
+                .filter(|piece| **piece == Piece::Piece(colour))
+                .count()
+        };
+        let reserves: String = Colour::playing(board.config.players)
+            .iter()
+            .map(|&colour| format!("  {:?} {}", colour, board.config.pieces - on_board(colour)))
+            .collect();
+        queue!(
+            self.stdout,
+            cursor::MoveTo(self.layout.history_column, 1),
+            Print(format!("Reserve{}", reserves)),
+            cursor::MoveTo(self.layout.history_column, 3),
+            Print("History"),
+        )?;
+        let rounds = &self.game.rounds;
+        let visible = rounds.len().saturating_sub(10);
+        for (i, round) in rounds.iter().enumerate().skip(visible) {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(self.layout.history_column, 4 + (i - visible) as u16),
+                Print(format!("{:>3}. {:?} {}", i + 1, round.proposed.colour(), notation::round_to_string(round)))
+            )?;
+        }
+
+        let status = match &self.game.phase {
+            Phase::Propose if self.game.board.is_placing() => format!("{:?}, place a piece.", to_move),
+            Phase::Propose => format!("{:?}, move a piece.", to_move),
+            Phase::SecondBest(turn) => format!(
+                "{:?} proposes to {}. {:?}, second best?",
+                to_move,
+                IO::turn_string(turn),
+                self.game.waiting_for().unwrap_or(to_move)
+            ),
+            Phase::Retry(_) => format!("{:?}, play another turn.", to_move),
+            Phase::Over(outcome) => match outcome.result {
+                EndState::Win(colour) => format!(
+                    "{:?} has won the game {}! Press any key.",
+                    colour,
+                    IO::reason_string(outcome, &self.game.board.config)
+                ),
+                EndState::Draw => {
+                    format!("The game is a draw {}. Press any key.", IO::reason_string(outcome, &self.game.board.config))
+                }
+            },
+        };
+        queue!(
+            self.stdout,
+            cursor::MoveTo(2, self.layout.status_row),
+            Print(status),
+            cursor::MoveTo(2, self.layout.status_row + 1),
+            Print(&self.message)
+        )?;
+        if let (Phase::SecondBest(_), true) = (&self.game.phase, human_to_act) {
+            for (button, focused) in [(CALL_BUTTON, self.call_focused), (PASS_BUTTON, !self.call_focused)] {
+                queue!(self.stdout, cursor::MoveTo(button.0, self.layout.button_row))?;
+                if focused {
+                    queue!(self.stdout, SetAttribute(Attribute::Reverse))?;
+                }
+                queue!(self.stdout, Print(button.1), SetAttribute(Attribute::Reset))?;
+            }
+        }
+        queue!(
+            self.stdout,
+            cursor::MoveTo(2, self.layout.button_row + 2),
+            Print(format!("arrows/1-{} select  enter confirm  esc cancel  s/n second best  q quit", self.layout.stacks.len()))
+        )?;
+        self.stdout.flush()
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    eval::{self, Weights, FEATURES},
+    game::*,
+    notation,
+    rules::RuleSet,
+};
+
+// Smallest change of a weight tried before tuning stops
+const MIN_STEP: f64 = 0.01;
+
+// A position of a recorded game with the result of the game for white,
+// 1 for a win, 0 for a loss and a half for a draw
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub features: [f64; FEATURES],
+    pub result: f64,
+}
+
+// Read a collection of games, one record per line optionally followed by its result: w, b or draw.
+// Without a result the game has to end on the board, unfinished games are left out.
+// Only quiet positions are used, where no colour can win with its next turn
+pub fn read_collection(text: &str, rules: &RuleSet) -> Result<Vec<Sample>, String> {
+    let mut samples = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (record, result) = match line.rsplit_once(char::is_whitespace) {
+            Some((record, "w")) => (record, Some(1.0)),
+            Some((record, "b")) => (record, Some(0.0)),
+            Some((record, "draw")) => (record, Some(0.5)),
+            _ => (line, None),
+        };
+        let rounds = notation::parse_record(record, rules.board.players).ok_or_else(|| format!("line {}: invalid record", i + 1))?;
+        let game = Game::from_rounds_with_rules(&rounds, rules.clone()).map_err(|error| format!("line {}: {}", i + 1, error))?;
+        let result = match (result, &game.phase) {
+            (Some(result), _) => result,
+            (None, Phase::Over(outcome)) => match outcome.result {
+                EndState::Win(Colour::White) => 1.0,
+                EndState::Win(_) => 0.0,
+                EndState::Draw => 0.5,
+            },
+            (None, _) => continue,
+        };
+
+        let mut board = Board::with_config(rules.board);
+        for (round, played) in rounds.iter().enumerate() {
+            board.do_turn(played.played());
+            let to_move = Colour::ALL[(round + 1) % rules.board.players];
+            if rules.is_won(&board, played.played().colour()).is_none() && is_quiet(&board, rules, to_move) {
+                samples.push(Sample {
+                    features: eval::features(&board, Colour::White),
+                    result,
+                });
+            }
+        }
+    }
+    Ok(samples)
+}
+
+// Whether neither the colour to move nor the one after it can win with its next turn
+fn is_quiet(board: &Board, rules: &RuleSet, to_move: Colour) -> bool {
+    [to_move, to_move.next(rules.board.players)].iter().all(|colour| {
+        rules.possible_turns(board, *colour).iter().all(|turn| {
+            let mut new_board = board.clone();
+            new_board.do_turn(turn);
+            rules.is_won(&new_board, *colour) != Some(EndState::Win(*colour))
+        })
+    })
+}
+
+// The mean squared difference between the results and the predictions of the weights
+pub fn error(weights: &Weights, samples: &[Sample]) -> f64 {
+    let total: f64 = samples
+        .iter()
+        .map(|sample| {
+            let score: f64 = sample.features.iter().zip(weights.0.iter()).map(|(feature, weight)| feature * weight).sum();
+            (sample.result - eval::win_probability(score)).powi(2)
+        })
+        .sum();
+    total / samples.len().max(1) as f64
+}
+
+// Texel tuning: change one weight at a time by a step in either direction and keep the change if
+// the error drops, halving the step once no change helps. The progress callback gets the step,
+// the error and the weights after every pass over the weights
+pub fn tune(mut weights: Weights, samples: &[Sample], mut progress: impl FnMut(f64, f64, &Weights)) -> Weights {
+    let mut best = error(&weights, samples);
+    let mut step = 1.0;
+    while step >= MIN_STEP {
+        let mut improved = false;
+        for i in 0..FEATURES {
+            for change in [step, -step] {
+                let mut candidate = weights;
+                candidate.0[i] += change;
+                let candidate_error = error(&candidate, samples);
+                if candidate_error < best {
+                    weights = candidate;
+                    best = candidate_error;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        progress(step, best, &weights);
+        if !improved {
+            step /= 2.0;
+        }
+    }
+    weights
+}
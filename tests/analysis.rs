@@ -0,0 +1,44 @@
+use second_best::{
+    analysis,
+    game::{Colour, Game, Turn},
+    notation,
+    rules::RuleSet,
+};
+
+#[test]
+fn three_player_games_are_summed_up_for_every_colour() {
+    let mut rules = RuleSet::default();
+    rules.set("variant", "three").unwrap();
+    let mut game = Game::with_rules(rules);
+    // Black lets the turn pass, Red calls second best
+    game.propose_turn(Turn::Place(Colour::White, 0)).unwrap();
+    game.decide_second_best(Colour::Black, false).unwrap();
+    game.decide_second_best(Colour::Red, true).unwrap();
+    game.retry_turn(Turn::Place(Colour::White, 1)).unwrap();
+
+    let rounds = analysis::analyse_game(&game, 1);
+    assert_eq!(rounds[0].second_best.caller, Some(Colour::Red));
+    let record = analysis::annotated_record(&rounds, 3);
+    let summary: Vec<&str> = record.lines().skip(1).map(|line| line.split(':').next().unwrap()).collect();
+    assert_eq!(summary, vec!["White", "Black", "Red"]);
+}
+
+#[test]
+fn rounds_without_another_turn_need_no_second_best() {
+    let mut rules = RuleSet::default();
+    for (name, value) in [("stacks", "3"), ("height", "3"), ("pieces", "4")] {
+        rules.set(name, value).unwrap();
+    }
+    // Black has room left on the third stack only
+    let rounds = notation::parse_record("1 1 1 2 2 2 3 3", 2).unwrap();
+    let before = Game::from_rounds_with_rules(&rounds[..7], rules.clone()).unwrap();
+    assert_eq!(before.legal_turns(), vec![Turn::Place(Colour::Black, 2)]);
+    let game = Game::from_rounds_with_rules(&rounds, rules).unwrap();
+
+    let analysis = analysis::analyse_game(&game, 1);
+    let last = &analysis[7].second_best;
+    assert!(last.could_call.is_empty());
+    assert!(!last.should_call);
+    assert_eq!(last.drop, 0.0);
+    assert!(!analysis::annotated_record(&analysis, 2).contains("inf"));
+}
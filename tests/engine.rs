@@ -0,0 +1,133 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    time::Duration,
+};
+
+use second_best::{
+    external::ExternalEngine,
+    game::{Colour, EndReason, Game, Turn},
+    notation,
+    player::{Decision, Player},
+    rules::RuleSet,
+};
+
+// The built binary speaking the engine protocol
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_second_best"))
+            .arg("--engine")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Self { child, stdin, stdout }
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stdin, "{}", line).unwrap();
+    }
+
+    fn receive(&mut self) -> String {
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        line.trim_end().to_owned()
+    }
+
+    // The lines up to the one starting with the prefix
+    fn receive_until(&mut self, prefix: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.receive();
+            let done = line.starts_with(prefix) || line.is_empty();
+            lines.push(line);
+            if done {
+                return lines;
+            }
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn positions_are_replayed_as_games() {
+    let mut engine = Engine::start();
+    // Moving before all pieces are placed
+    engine.send("position startpos rounds 1 2 1-2");
+    engine.send("isready");
+    assert_eq!(engine.receive_until("readyok"), vec!["info string illegal rounds: that turn is not possible", "readyok"]);
+
+    // Second best called without calls left
+    engine.send("rules calls 0");
+    engine.send("position startpos rounds 1/2");
+    engine.send("isready");
+    assert!(engine.receive_until("readyok")[0].starts_with("info string illegal rounds: "));
+
+    engine.send("position startpos rounds 1 2");
+    engine.send("go depth 1");
+    let lines = engine.receive_until("bestmove");
+    assert!(lines.last().unwrap().starts_with("bestmove "), "{:?}", lines);
+}
+
+#[test]
+fn external_engines_play_with_the_rules_of_the_game() {
+    let mut rules = RuleSet::default();
+    rules.set("distances", "2").unwrap();
+    let command = format!("{} --engine", env!("CARGO_BIN_EXE_second_best"));
+    let mut engine = ExternalEngine::launch(Colour::White, &command, Duration::from_millis(200), &rules).unwrap();
+
+    // With the standard rules every move would go to a neighbour or the opposite stack
+    let mut game = Game::with_rules(rules.clone());
+    game.board = notation::parse_board("w/b/wb/bw/bw/wb/wbw/bwb", rules.board).unwrap();
+    let Decision::Play(turn) = engine.ask_turn(&game) else {
+        panic!("the engine forfeited: {:?}", engine.forfeit_reason());
+    };
+    assert!(game.legal_turns().contains(&turn));
+    let Turn::Move(_, from, to) = turn else {
+        panic!("{:?} is not a move", turn);
+    };
+    assert_eq!(game.board.ring_distance(from, to), 2);
+}
+
+// An engine that completes the handshake and then answers a search as the script says
+fn scripted_engine(name: &str, on_go: &str) -> String {
+    let path = std::env::temp_dir().join(format!("second_best_{}_{}.sh", name, std::process::id()));
+    let script = format!(
+        "while read line; do\n  case \"$line\" in\n    sbp) echo sbpok ;;\n    isready) echo readyok ;;\n    go*) {} ;;\n    quit) exit ;;\n  esac\ndone\n",
+        on_go
+    );
+    std::fs::write(&path, script).unwrap();
+    format!("sh {}", path.display())
+}
+
+#[test]
+fn failing_engines_forfeit() {
+    let game = Game::new();
+    let cases = [
+        ("illegal", "echo bestmove 1-2", "engine played illegal turn 1-2", EndReason::Forfeit),
+        ("crash", "exit", "engine exited", EndReason::Forfeit),
+        ("silent", "true", "engine timed out", EndReason::Timeout),
+    ];
+    for (name, on_go, reason, end_reason) in cases {
+        let command = scripted_engine(name, on_go);
+        let mut engine = ExternalEngine::launch(Colour::White, &command, Duration::from_millis(100), &RuleSet::default()).unwrap();
+        assert!(matches!(engine.ask_turn(&game), Decision::Resign), "{}", name);
+        assert_eq!(engine.forfeit_reason().as_deref(), Some(reason));
+        assert_eq!(engine.forfeit_end_reason(), end_reason);
+    }
+    assert!(ExternalEngine::launch(Colour::White, "true", Duration::from_millis(100), &RuleSet::default()).is_err());
+}
@@ -0,0 +1,34 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+// People play both colours in the terminal and ask for hints at the prompts
+#[test]
+fn people_get_hints_for_turns_and_second_best() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_second_best"))
+        .args(["--seed", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "p\nhint\n1\nhint").unwrap();
+
+    let hints: Vec<String> = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .map(Result::unwrap)
+        .filter(|line| line.starts_with("Hint: "))
+        .take(2)
+        .collect();
+    let _ = child.kill();
+    let _ = child.wait();
+
+    assert!(hints[0].starts_with("Hint: place a piece at "), "{}", hints[0]);
+    assert!(hints[0].contains("(evaluation "), "{}", hints[0]);
+    assert!(
+        hints[1].starts_with("Hint: call second best") || hints[1].starts_with("Hint: let it pass"),
+        "{}",
+        hints[1]
+    );
+}
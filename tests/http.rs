@@ -0,0 +1,191 @@
+#![cfg(feature = "serde")]
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use second_best::{http, rules::RuleSet};
+use serde_json::{json, Value};
+
+fn start_server() -> SocketAddr {
+    start_server_with(RuleSet::default())
+}
+
+fn start_server_with(rules: RuleSet) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || http::serve(listener, rules));
+    address
+}
+
+// The status, headers and body of the response, the headers of the request end with a line break each
+fn raw_request(address: SocketAddr, method: &str, path: &str, headers: &str, body: &str) -> (u16, String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}", method, path, headers, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head[9..12].parse().unwrap(), head.to_owned(), body.to_owned())
+}
+
+fn request(address: SocketAddr, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+    let headers = if body.is_some() { "Content-Type: application/json\r\n" } else { "" };
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let (status, _, body) = raw_request(address, method, path, headers, &body);
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+fn place(colour: &str, to: usize) -> Value {
+    json!({"type": "place", "colour": colour, "to": to})
+}
+
+#[test]
+fn people_play_a_game() {
+    let address = start_server();
+    let (status, state) = request(address, "POST", "/games", Some(json!({"players": ["human", "human"]})));
+    assert_eq!(status, 201);
+    let id = state["id"].as_u64().unwrap();
+    assert_eq!(state["waiting_for"], "white");
+    assert_eq!(state["legal_turns"].as_array().unwrap().len(), 8);
+
+    let turns = format!("/games/{}/turns", id);
+    assert_eq!(request(address, "POST", &turns, Some(place("white", 8))).0, 422);
+    assert_eq!(request(address, "POST", &turns, Some(place("black", 0))).0, 409);
+    let (status, state) = request(address, "POST", &turns, Some(place("white", 0)));
+    assert_eq!(status, 200);
+    assert_eq!(state["waiting_for"], "black");
+    assert_eq!(state["can_call_second_best"], true);
+
+    let second_best = format!("/games/{}/second-best", id);
+    let (status, state) = request(address, "POST", &second_best, Some(json!({"colour": "black", "call": true})));
+    assert_eq!(status, 200);
+    assert_eq!(state["game"]["phase"], json!({"retry": place("white", 0)}));
+    assert_eq!(request(address, "POST", &turns, Some(place("white", 0))).0, 422);
+    let (_, state) = request(address, "POST", &turns, Some(place("white", 1)));
+    assert_eq!(state["game"]["rounds"], json!([{"proposed": place("white", 0), "second_turn": place("white", 1)}]));
+
+    let (status, state) = request(address, "POST", &format!("/games/{}/resign", id), Some(json!({"colour": "black"})));
+    assert_eq!(status, 200);
+    assert_eq!(state["waiting_for"], Value::Null);
+    assert_eq!(request(address, "POST", &turns, Some(place("black", 2))).0, 409);
+}
+
+#[test]
+fn calls_without_calls_left_are_refused() {
+    let address = start_server();
+    let setup = json!({"players": ["human", "human"], "rules": {"second_best_calls": 0}});
+    let (_, state) = request(address, "POST", "/games", Some(setup));
+    let id = state["id"].as_u64().unwrap();
+    request(address, "POST", &format!("/games/{}/turns", id), Some(place("white", 0)));
+    let second_best = format!("/games/{}/second-best", id);
+    let (status, body) = request(address, "POST", &second_best, Some(json!({"colour": "black", "call": true})));
+    assert_eq!(status, 422);
+    assert!(body["error"].is_string());
+    let (status, state) = request(address, "POST", &second_best, Some(json!({"colour": "black", "call": false})));
+    assert_eq!(status, 200);
+    assert_eq!(state["game"]["rounds"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn bad_requests_are_answered_with_errors() {
+    let address = start_server();
+    assert_eq!(request(address, "GET", "/games/3", None).0, 404);
+    assert_eq!(request(address, "GET", "/nothing", None).0, 404);
+    assert_eq!(request(address, "DELETE", "/games", None).0, 405);
+    let (status, body) = request(address, "POST", "/games", Some(json!({"players": "everyone"})));
+    assert_eq!(status, 400);
+    assert!(body["error"].is_string());
+    assert_eq!(request(address, "POST", "/games", Some(json!({"players": ["human"]}))).0, 422);
+    assert_eq!(request(address, "POST", "/games", Some(json!({"players": ["human", {"bot": http::MAX_DEPTH + 1}]}))).0, 422);
+
+    // Bodies are JSON, and the API is only for the page it serves and local tools
+    let body = json!({"players": ["human", "human"]}).to_string();
+    assert_eq!(raw_request(address, "POST", "/games", "", &body).0, 415);
+    assert_eq!(raw_request(address, "POST", "/games", "Content-Type: text/plain\r\n", &body).0, 415);
+    let (status, head, _) = raw_request(address, "POST", "/games", "Content-Type: application/json; charset=utf-8\r\n", &body);
+    assert_eq!(status, 201);
+    assert!(!head.contains("Access-Control-Allow-Origin"));
+    assert_eq!(request(address, "OPTIONS", "/games", None).0, 405);
+    assert_eq!(request(address, "GET", &format!("/games/0/analysis?depth={}", http::MAX_DEPTH + 1), None).0, 422);
+}
+
+#[test]
+fn bots_play_their_turns() {
+    let address = start_server();
+    let (_, state) = request(address, "POST", "/games", Some(json!({"players": [{"bot": 1}, "human"]})));
+    let path = format!("/games/{}", state["id"]);
+    let start = Instant::now();
+    let state = loop {
+        let (_, state) = request(address, "GET", &path, None);
+        if state["waiting_for"] == "black" {
+            break state;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "the bot did not move");
+        thread::sleep(Duration::from_millis(20));
+    };
+    assert_eq!(state["game"]["phase"]["second_best"]["colour"], "white");
+    assert_eq!(request(address, "POST", &format!("{}/turns", path), Some(place("white", 0))).0, 409);
+
+    let (status, analysis) = request(address, "GET", &format!("{}/analysis?depth=1", path), None);
+    assert_eq!(status, 200);
+    assert_eq!(analysis["colour"], "white");
+    assert_eq!(analysis["turns"].as_array().unwrap().len(), 8);
+    assert!(analysis["call_second_best"].is_boolean());
+}
+
+#[test]
+fn the_page_is_served() {
+    let address = start_server();
+    let (status, head, body) = raw_request(address, "GET", "/", "", "");
+    assert_eq!(status, 200);
+    assert!(head.contains("Content-Type: text/html"));
+    assert!(body.contains("<svg id=\"board\""));
+}
+
+// The fields the script of the page reads from the states it gets
+#[test]
+fn states_have_what_the_page_reads() {
+    let address = start_server();
+    let (_, state) = request(address, "POST", "/games", Some(json!({"players": ["human", "human"]})));
+    assert_eq!(state["players"], json!(["human", "human"]));
+    assert_eq!(state["waiting_for"], "white");
+    assert!(state["legal_turns"].is_array());
+    assert!(state["can_call_second_best"].is_boolean());
+    let config = &state["game"]["board"]["config"];
+    for field in ["pieces", "players", "height"] {
+        assert!(config[field].is_u64(), "{}", field);
+    }
+    assert_eq!(state["game"]["board"]["places"][0], json!([null, null, null]));
+    assert_eq!(state["game"]["phase"], "propose");
+
+    let id = state["id"].as_u64().unwrap();
+    let (_, state) = request(address, "POST", &format!("/games/{}/turns", id), Some(place("white", 0)));
+    assert_eq!(state["game"]["phase"], json!({"second_best": place("white", 0)}));
+    let (_, state) = request(address, "POST", &format!("/games/{}/resign", id), Some(json!({"colour": "black"})));
+    assert_eq!(state["game"]["phase"]["over"], json!({"result": {"win": "white"}, "reason": "resignation", "stacks": []}));
+
+    // Errors come with a message the page shows
+    let (status, body) = request(address, "GET", "/games/99", None);
+    assert_eq!(status, 404);
+    assert!(body["error"].is_string());
+}
+
+// The page asks for the rules to offer a seat for every colour
+#[test]
+fn the_page_seats_every_colour_of_the_rules() {
+    let mut rules = RuleSet::default();
+    rules.set("variant", "three").unwrap();
+    let address = start_server_with(rules);
+    let (_, _, page) = raw_request(address, "GET", "/", "", "");
+    assert!(page.contains("api(\"GET\", \"/rules\")"));
+
+    let (status, rules) = request(address, "GET", "/rules", None);
+    assert_eq!(status, 200);
+    assert_eq!(rules["board"]["players"], 3);
+    let seats = json!({"players": ["human", {"bot": 1}, {"bot": 1}]});
+    assert_eq!(request(address, "POST", "/games", Some(seats)).0, 201);
+    assert_eq!(request(address, "POST", "/rules", None).0, 405);
+}
@@ -0,0 +1,16 @@
+use second_best::{
+    game::{BoardConfig, Colour, EndReason, EndState, Outcome},
+    io::IO,
+};
+
+#[test]
+fn lines_are_told_by_their_length() {
+    let outcome = Outcome {
+        result: EndState::Win(Colour::White),
+        reason: EndReason::RingLine,
+        stacks: vec![0, 1, 2],
+    };
+    let config = BoardConfig { line: 3, ..BoardConfig::STANDARD };
+    assert_eq!(IO::reason_string(&outcome, &config), "with three in a row on 1, 2, 3");
+    assert_eq!(IO::reason_string(&outcome, &BoardConfig::STANDARD), "with four in a row on 1, 2, 3");
+}
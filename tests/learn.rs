@@ -0,0 +1,62 @@
+use second_best::{
+    eval::{self, Weights, FEATURE_NAMES, PROBABILITY_SCALE},
+    game::{Board, BoardConfig, Colour},
+    learn,
+    notation,
+};
+
+fn board(board: &str) -> Board {
+    notation::parse_board(board, BoardConfig::STANDARD).expect("valid board")
+}
+
+#[test]
+fn td_update_moves_predictions_towards_the_result() {
+    let positions = [board("w/w/-/-/-/b/-/-"), board("w/w/-/b/-/b/w/-")];
+    let last = positions.last().unwrap();
+    for result in [0.0, 1.0] {
+        let mut weights = Weights::STANDARD;
+        learn::td_update(&mut weights, &positions, result, 0.5, 0.7);
+        let before = learn::predict(&Weights::STANDARD, last);
+        let after = learn::predict(&weights, last);
+        assert!((result - after).abs() < (result - before).abs(), "{} -> {} for {}", before, after, result);
+    }
+}
+
+#[test]
+fn td_update_without_lambda_only_moves_towards_the_next_position() {
+    let (first, last) = (board("w/w/-/-/-/b/-/-"), board("w/w/-/b/-/b/w/-"));
+    let (rate, weights) = (0.5, Weights::STANDARD);
+    // The last position already predicts the result, so only the first one is moved towards it
+    let (predicted, target) = (learn::predict(&weights, &first), learn::predict(&weights, &last));
+    let mut updated = weights;
+    learn::td_update(&mut updated, &[first.clone(), last], target, rate, 0.0);
+    let slope = predicted * (1.0 - predicted) / PROBABILITY_SCALE;
+    for (i, feature) in eval::features(&first, Colour::White).iter().enumerate() {
+        let expected = weights.0[i] + rate * (target - predicted) * slope * feature;
+        assert!((updated.0[i] - expected).abs() < 1e-12, "{} instead of {}", updated.0[i], expected);
+    }
+
+    // Nothing is learned from a game without positions
+    let mut weights = Weights::STANDARD;
+    learn::td_update(&mut weights, &[], 1.0, rate, 0.7);
+    assert_eq!(weights, Weights::STANDARD);
+}
+
+#[test]
+fn weights_survive_writing_and_reading() {
+    let weights = Weights([1.25, -0.003, 12.5e-7, 1234.5678]);
+    assert_eq!(Weights::parse(&weights.to_text()), Ok(weights));
+    // Left out features keep their standard weight
+    let text = format!("# comment\n\n{} 4.5\n", FEATURE_NAMES[0]);
+    let mut expected = Weights::STANDARD;
+    expected.0[0] = 4.5;
+    assert_eq!(Weights::parse(&text), Ok(expected));
+}
+
+#[test]
+fn bad_weight_files_are_rejected() {
+    for text in ["tops", "tops two", "corners 1.0", "tops 1.0 2.0"] {
+        assert!(Weights::parse(text).is_err(), "{}", text);
+    }
+    assert!(Weights::load("no/such/weights/file").is_err());
+}
@@ -0,0 +1,106 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use second_best::{
+    game::{Colour, Game, Turn},
+    network::{self, Message, RemotePlayer, NETWORK_VERSION},
+    player::Player,
+    rules::RuleSet,
+};
+
+fn read_line(reader: &mut impl BufRead) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    line.trim().to_owned()
+}
+
+fn other_rules() -> RuleSet {
+    let mut rules = RuleSet::default();
+    rules.set("stacks", "10").unwrap();
+    rules.set("replayvetoed", "yes").unwrap();
+    rules
+}
+
+#[test]
+fn messages_round_trip() {
+    let mut variant = other_rules();
+    for (name, value) in [("distances", "1,3"), ("calls", "2"), ("simultaneous", "loser"), ("variant", "three")] {
+        variant.set(name, value).unwrap();
+    }
+    let messages = [
+        Message::Hello(NETWORK_VERSION, Some(Colour::White), variant),
+        Message::Hello(NETWORK_VERSION, Some(Colour::Black), RuleSet::default()),
+        Message::Hello(NETWORK_VERSION, None, other_rules()),
+        Message::Propose(Turn::Place(Colour::White, 2)),
+        Message::Propose(Turn::Move(Colour::White, 0, 7)),
+        Message::SecondBest(true),
+        Message::SecondBest(false),
+        Message::Retry(Turn::Move(Colour::White, 3, 4)),
+        Message::Resign,
+        Message::GameOver("illegal turn 9".to_owned()),
+    ];
+    for message in messages {
+        assert_eq!(Message::parse(&message.to_line(), Colour::White), Some(message));
+    }
+    assert_eq!(Message::parse("hello 3 r", Colour::White), None);
+    assert_eq!(Message::parse("hello 3 w stacks", Colour::White), None);
+    assert_eq!(Message::parse("propose 0", Colour::White), None);
+    assert_eq!(Message::parse("secondbest maybe", Colour::White), None);
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let stream = TcpStream::connect(address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let hello = read_line(&mut reader);
+        writeln!(&stream, "hello {}", NETWORK_VERSION + 1).unwrap();
+        hello
+    });
+    let error = network::accept(&listener, Colour::White, &RuleSet::default()).err().expect("the version is refused");
+    assert!(error.to_string().contains("version"), "{}", error);
+    assert!(peer.join().unwrap().starts_with(&format!("hello {} w stacks 8 ", NETWORK_VERSION)));
+
+    let host = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        writeln!(&stream, "hello {} w", NETWORK_VERSION + 1).unwrap();
+    });
+    assert!(network::join(address, &RuleSet::default()).is_err());
+    host.join().unwrap();
+}
+
+#[test]
+fn other_rules_are_refused_by_both_sides() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || network::join(address, &other_rules()).err().map(|error| error.to_string()));
+    let error = network::accept(&listener, Colour::White, &RuleSet::default()).err().expect("the rules are refused");
+    assert_eq!(error.to_string(), "peer plays with other rules: stacks 10 instead of 8, replayvetoed yes instead of no");
+    assert_eq!(peer.join().unwrap().expect("the rules are refused"), "peer plays with other rules: stacks 8 instead of 10, replayvetoed no instead of yes");
+}
+
+#[test]
+fn illegal_turns_forfeit() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let stream = TcpStream::connect(address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        read_line(&mut reader);
+        writeln!(&stream, "{}", Message::Hello(NETWORK_VERSION, None, RuleSet::default()).to_line()).unwrap();
+        // There is no stack 9 on the standard board
+        writeln!(&stream, "propose 9").unwrap();
+        read_line(&mut reader)
+    });
+    let (connection, colour) = network::accept(&listener, Colour::Black, &RuleSet::default()).unwrap();
+    assert_eq!(colour, Colour::White);
+    let mut remote = RemotePlayer::new(colour, connection);
+    remote.ask_turn(&Game::new());
+    assert_eq!(remote.forfeit_reason(), Some("illegal turn 9".to_owned()));
+    assert_eq!(peer.join().unwrap(), "gameover illegal turn 9");
+}
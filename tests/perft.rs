@@ -0,0 +1,99 @@
+use second_best::{
+    game::{Board, BoardConfig, Colour, Game, GameError, Phase, Turn},
+    notation,
+    perft::{divide, perft},
+    rules::RuleSet,
+};
+
+fn count(rules: &RuleSet, board: &str, colour: Colour, depth: u32, vetoes: bool) -> u64 {
+    let board = notation::parse_board(board, rules.board).expect("valid board");
+    perft(&board, rules, colour, depth, vetoes)
+}
+
+fn start(rules: &RuleSet, depth: u32, vetoes: bool) -> u64 {
+    perft(&Board::with_config(rules.board), rules, Colour::White, depth, vetoes)
+}
+
+#[test]
+fn standard_start() {
+    let rules = RuleSet::default();
+    let counts: Vec<u64> = (0..=5).map(|depth| start(&rules, depth, false)).collect();
+    // The fourth piece can not go on a stack filled by the first three
+    assert_eq!(counts, vec![1, 8, 64, 512, 4088, 32480]);
+}
+
+#[test]
+fn standard_start_with_vetoes() {
+    let rules = RuleSet::default();
+    assert_eq!(start(&rules, 1, true), 64);
+    assert_eq!(start(&rules, 2, true), 4096);
+}
+
+#[test]
+fn replaying_vetoed_turns() {
+    let rules = RuleSet { replay_vetoed: true, ..RuleSet::default() };
+    assert_eq!(start(&rules, 1, true), 72);
+    assert_eq!(start(&rules, 2, true), 5184);
+}
+
+#[test]
+fn three_player_start() {
+    let rules = RuleSet { board: BoardConfig::THREE_PLAYER, ..RuleSet::default() };
+    assert_eq!(start(&rules, 1, false), 12);
+    assert_eq!(start(&rules, 2, false), 144);
+    assert_eq!(start(&rules, 3, false), 1728);
+}
+
+#[test]
+fn moving_phase() {
+    let rules = RuleSet::default();
+    let board = "w/b/wb/bw/bw/wb/wbw/bwb";
+    assert_eq!(count(&rules, board, Colour::White, 1, false), 9);
+    assert_eq!(count(&rules, board, Colour::White, 2, false), 77);
+    assert_eq!(count(&rules, board, Colour::White, 3, false), 447);
+}
+
+#[test]
+fn move_distances() {
+    let mut rules = RuleSet::default();
+    rules.set("distances", "1,2").unwrap();
+    // Only moves over the allowed distances are counted
+    let board = notation::parse_board("w/b/wb/bw/bw/wb/wbw/bwb", rules.board).unwrap();
+    let turns = divide(&board, &rules, Colour::White, 1, false);
+    assert!(turns.iter().all(|(turn, nodes)| *nodes == 1 && rules.is_possible_turn(&board, turn)));
+    assert_eq!(turns.len(), rules.possible_turns(&board, Colour::White).len());
+}
+
+#[test]
+fn finished_games_have_no_turns() {
+    let rules = RuleSet::default();
+    let board = "w/w/w/w/b/b/b/-";
+    assert_eq!(count(&rules, board, Colour::Black, 0, false), 1);
+    assert_eq!(count(&rules, board, Colour::Black, 1, false), 0);
+}
+
+#[test]
+fn legal_turns_after_second_best() {
+    let mut game = Game::new();
+    assert_eq!(game.legal_turns().len(), 8);
+    game.propose_turn(Turn::Place(Colour::White, 0)).unwrap();
+    assert!(game.legal_turns().is_empty());
+    game.decide_second_best(Colour::Black, true).unwrap();
+    let turns = game.legal_turns();
+    assert_eq!(turns.len(), 7);
+    assert!(!turns.contains(&Turn::Place(Colour::White, 0)));
+}
+
+#[test]
+fn second_best_is_refused_without_calls_left() {
+    let rules = RuleSet {
+        second_best_calls: Some(0),
+        ..RuleSet::default()
+    };
+    let mut game = Game::with_rules(rules);
+    game.propose_turn(Turn::Place(Colour::White, 0)).unwrap();
+    assert_eq!(game.decide_second_best(Colour::Black, true), Err(GameError::SecondBestNotAllowed));
+    assert_eq!(game.phase, Phase::SecondBest(Turn::Place(Colour::White, 0)));
+    game.decide_second_best(Colour::Black, false).unwrap();
+    assert_eq!(game.rounds.len(), 1);
+}
@@ -0,0 +1,29 @@
+use std::{thread, time::Duration};
+
+use second_best::{bot::Bot, game::*, player::Player};
+
+fn bot(depth: u64) -> Bot {
+    Bot::new(Colour::White, depth).with_threads(1)
+}
+
+#[test]
+fn pondered_positions_score_as_fresh_searches() {
+    let mut game = Game::new();
+    let mut pondering = bot(2).with_pondering(true);
+    let turn = Turn::Place(Colour::White, 0);
+    game.propose_turn(turn.clone()).unwrap();
+    game.decide_second_best(Colour::Black, false).unwrap();
+    // Black is to move, the bot ponders its replies until stopped by the end of the game
+    pondering.turn_played(&game, &turn);
+    thread::sleep(Duration::from_millis(1000));
+    pondering.game_over(&game, &Outcome::new(EndState::Draw, EndReason::Resignation));
+
+    for reply in [1, 4] {
+        let mut board = game.board.clone();
+        board.do_turn(&Turn::Place(Colour::Black, reply));
+        let scores = pondering.score_turns(&board, Colour::White);
+        assert_eq!(scores, bot(2).score_turns(&board, Colour::White), "after black placed on {}", reply + 1);
+    }
+    // The scores were taken from the pondering, nothing was searched
+    assert_eq!(pondering.stats().nodes, 0);
+}
@@ -0,0 +1,141 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use second_best::{
+    game::{Board, BoardConfig, Colour, EndState, Game, Phase, Piece, Turn},
+    rules::RuleSet,
+};
+
+// Games are cut off after this many rounds, random play rarely ends a game in the moving phase
+const MAX_ROUNDS: usize = 200;
+
+// Rotate the ring so that stack i ends up at i + steps
+fn rotate(board: &Board, steps: usize) -> Board {
+    let mut rotated = board.clone();
+    rotated.places.rotate_right(steps % board.places.len());
+    rotated
+}
+
+fn check_board(board: &Board) {
+    let config = board.config;
+    for colour in Colour::playing(config.players) {
+        let count = board
+            .places
+            .iter()
+            .flat_map(|place| place.0.iter())
+            .filter(|piece| **piece == Piece::Piece(*colour))
+            .count();
+        assert!(count <= config.pieces, "{:?} has {} pieces on {:?}", colour, count, board);
+    }
+    for place in &board.places {
+        assert_eq!(place.0.len(), config.height, "stack of the wrong height on {:?}", board);
+        let count = place.count_pieces();
+        assert!(place.0[count..].iter().all(|piece| *piece == Piece::Blank), "floating piece on {:?}", board);
+        // Panics on floating pieces
+        let top = place.peek_top();
+        assert_eq!(*top == Piece::Blank, count == 0);
+    }
+    let result = board.is_won();
+    for steps in 1..board.places.len() {
+        assert_eq!(rotate(board, steps).is_won(), result, "rotating by {} changes {:?}", steps, board);
+    }
+}
+
+// Every turn can be taken back, and doing it again reaches the same board
+fn check_undo(board: &Board, turns: &[Turn]) {
+    for turn in turns {
+        let mut new_board = board.clone();
+        new_board.do_turn(turn);
+        let after = new_board.clone();
+        new_board.undo_turn(turn);
+        assert_eq!(&new_board, board, "undoing {:?}", turn);
+        new_board.do_turn(turn);
+        assert_eq!(new_board, after, "redoing {:?}", turn);
+    }
+}
+
+// Play a game with random turns and random second best calls, checking the board after every step
+fn play_random_game(rules: RuleSet, rng: &mut StdRng) {
+    let mut game = Game::with_rules(rules);
+    while game.rounds.len() < MAX_ROUNDS {
+        check_board(&game.board);
+        let previous = game.board.clone();
+        let turns = game.legal_turns();
+        check_undo(&game.board, &turns);
+        match game.phase.clone() {
+            Phase::Propose => {
+                let turn = turns.choose(rng).expect("a legal turn while the game is not over").clone();
+                game.propose_turn(turn).unwrap();
+            }
+            Phase::SecondBest(_) => {
+                let colour = game.waiting_for().unwrap();
+                let call = game.can_call_second_best() && rng.gen_bool(0.3);
+                game.decide_second_best(colour, call).unwrap();
+            }
+            Phase::Retry(vetoed) => {
+                assert!(game.rules.replay_vetoed || !turns.contains(&vetoed));
+                let turn = turns.choose(rng).expect("a legal turn after second best").clone();
+                game.retry_turn(turn).unwrap();
+            }
+            Phase::Over(outcome) => {
+                if outcome.stacks.is_empty() {
+                    assert!(game.board.is_won().is_none());
+                } else {
+                    assert!(game.board.is_won().is_some());
+                }
+                return;
+            }
+        }
+        if !matches!(game.phase, Phase::SecondBest(_)) && game.board != previous {
+            let last = game.turns.last().unwrap().clone();
+            let mut undone = game.board.clone();
+            undone.undo_turn(&last);
+            assert_eq!(undone, previous, "undoing {:?}", last);
+        }
+    }
+}
+
+#[test]
+fn random_standard_games() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..2000 {
+        play_random_game(RuleSet::default(), &mut rng);
+    }
+}
+
+#[test]
+fn random_games_with_rule_variations() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut rules = RuleSet::default();
+    for (name, value) in [("replayvetoed", "yes"), ("distances", "1,2,3"), ("simultaneous", "loser"), ("calls", "2")] {
+        rules.set(name, value).unwrap();
+        for _ in 0..250 {
+            play_random_game(rules.clone(), &mut rng);
+        }
+    }
+}
+
+#[test]
+fn random_games_on_other_boards() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let configs = [
+        BoardConfig::THREE_PLAYER,
+        BoardConfig { stacks: 10, height: 4, line: 5, pieces: 12, players: 2 },
+        BoardConfig { stacks: 5, height: 2, line: 3, pieces: 4, players: 2 },
+    ];
+    for config in configs {
+        assert!(config.is_valid());
+        for _ in 0..300 {
+            play_random_game(RuleSet { board: config, ..RuleSet::default() }, &mut rng);
+        }
+    }
+}
+
+#[test]
+fn rotation_keeps_the_winner() {
+    let mut board = Board::new();
+    for idx in [6, 7, 0, 1] {
+        board.do_turn(&Turn::Place(Colour::White, idx));
+    }
+    for steps in 0..8 {
+        assert_eq!(rotate(&board, steps).is_won(), Some(EndState::Win(Colour::White)));
+    }
+}
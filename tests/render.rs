@@ -0,0 +1,59 @@
+use second_best::{
+    game::*,
+    notation,
+    render::{self, AnsiRenderer, Highlight, Renderer, Style},
+};
+
+const POSITION: &str = "wb/w/b/-/bw/-/w/b";
+
+fn highlight() -> Highlight {
+    Highlight {
+        last_turn: Some(Turn::Move(Colour::White, 1, 6)),
+        winning_stacks: vec![4],
+    }
+}
+
+#[test]
+fn ascii_boards_go_round_the_ring() {
+    let board = notation::parse_board(POSITION, BoardConfig::STANDARD).unwrap();
+    let expected = "\
+4    [...] [XO.]    5
+3   [X..]   [...]   6
+2   [O..]   [O..]   7
+1    [OX.] [X..]    8
+";
+    assert_eq!(Style::Ascii.renderer().board(&board, &Highlight::default()), expected);
+    // Only the ANSI renderer highlights
+    assert_eq!(Style::Ascii.renderer().board(&board, &highlight()), expected);
+    assert_eq!(Style::parse("ascii"), Some(Style::Ascii));
+    assert_eq!(Style::parse("svg"), None);
+}
+
+#[test]
+fn ansi_stacks_show_the_last_turn_and_the_winning_line() {
+    let board = notation::parse_board(POSITION, BoardConfig::STANDARD).unwrap();
+    let stack = |idx: usize| AnsiRenderer.stack(&board.places[idx], idx, &highlight());
+    let white = "\x1b[1;97mO\x1b[0m";
+    let black = "\x1b[1;34mX\x1b[0m";
+    assert_eq!(stack(0), format!("[{}{}{}]", white, black, " "));
+    assert_eq!(stack(1), format!("\x1b[1;33m[\x1b[0m{}  \x1b[1;33m]\x1b[0m", white));
+    assert_eq!(stack(6), format!("\x1b[1;33m[\x1b[0m{}  \x1b[1;33m]\x1b[0m", white));
+    assert_eq!(stack(4), format!("\x1b[1;42m[\x1b[0m{}{} \x1b[1;42m]\x1b[0m", black, white));
+}
+
+#[test]
+fn svg_shows_every_piece_and_every_round() {
+    let board = notation::parse_board(POSITION, BoardConfig::STANDARD).unwrap();
+    let svg = render::svg_board(&board, &highlight());
+    assert!(svg.starts_with("<svg "));
+    assert_eq!(svg.matches("<circle ").count(), 8);
+    assert_eq!(svg.matches("<rect ").count(), 9);
+    assert_eq!(svg.matches("stroke=\"#2a2\"").count(), 1);
+    assert_eq!(svg.matches("stroke=\"#e90\"").count(), 2);
+
+    let rounds = notation::parse_record("1 2 3 4 5", 2).unwrap();
+    let svg = render::svg_game(&rounds, BoardConfig::STANDARD);
+    assert!(svg.contains("width=\"880\" height=\"500\""));
+    assert_eq!(svg.matches("<g ").count(), 5);
+    assert!(svg.contains(">5. White 5</text>"));
+}
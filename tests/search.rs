@@ -0,0 +1,166 @@
+use second_best::{
+    bot::Bot,
+    eval::{self, Weights},
+    game::{Board, Colour, EndState, Turn},
+    notation,
+    rules::RuleSet,
+};
+
+// Scores in hundredths of a point from the view of the colour to move, counted as the bot does
+const WIN: i32 = 10_000;
+const WIN_BOUND: i32 = WIN - 1_000;
+const QUIESCENCE_DEPTH: u32 = 6;
+
+// Positions with the colour to move, placing and moving
+const POSITIONS: [(&str, Colour); 4] = [
+    ("-/-/-/-/-/-/-/-", Colour::White),
+    ("w/b/-/w/-/b/-/-", Colour::White),
+    ("wb/w/b/-/bw/-/w/b", Colour::Black),
+    ("w/b/wb/bw/bw/wb/wbw/bwb", Colour::White),
+];
+
+fn evaluate(board: &Board, colour: Colour) -> i32 {
+    let score = (eval::evaluate(&Weights::default(), board, colour) * 100.0).round() as i32;
+    score.clamp(-WIN_BOUND + 1, WIN_BOUND - 1)
+}
+
+fn wins(rules: &RuleSet, board: &Board, turn: &Turn) -> bool {
+    let mut new_board = board.clone();
+    new_board.do_turn(turn);
+    rules.is_won(&new_board, turn.colour()) == Some(EndState::Win(turn.colour()))
+}
+
+// Every turn is searched while a colour threatens to win, without windows
+fn quiescence(rules: &RuleSet, board: &Board, colour: Colour, ply: i32, depth: u32) -> i32 {
+    let turns = rules.possible_turns(board, colour);
+    if turns.is_empty() {
+        return -(WIN - ply);
+    }
+    if turns.iter().any(|turn| wins(rules, board, turn)) {
+        return WIN - ply;
+    }
+    let opponent = colour.opposite();
+    let threatened = rules.possible_turns(board, opponent).iter().any(|turn| wins(rules, board, turn));
+    if !threatened || depth == 0 {
+        return evaluate(board, colour);
+    }
+    turns
+        .iter()
+        .map(|turn| {
+            let mut new_board = board.clone();
+            new_board.do_turn(turn);
+            match rules.is_won(&new_board, colour) {
+                Some(EndState::Win(_)) => -(WIN - ply),
+                Some(EndState::Draw) => 0,
+                None => -quiescence(rules, &new_board, opponent, ply + 1, depth - 1),
+            }
+        })
+        .max()
+        .unwrap()
+}
+
+// Plain minimax: every turn to the depth, no table, no ordering and no windows
+fn minimax(rules: &RuleSet, board: &Board, colour: Colour, depth: u64, ply: i32) -> i32 {
+    if depth == 0 {
+        return quiescence(rules, board, colour, ply, QUIESCENCE_DEPTH);
+    }
+    let turns = rules.possible_turns(board, colour);
+    if turns.is_empty() {
+        return -(WIN - ply);
+    }
+    turns.iter().map(|turn| score_turn(rules, turn, board, depth - 1, ply)).max().unwrap()
+}
+
+fn score_turn(rules: &RuleSet, turn: &Turn, board: &Board, depth: u64, ply: i32) -> i32 {
+    let mover = turn.colour();
+    let mut new_board = board.clone();
+    new_board.do_turn(turn);
+    match rules.is_won(&new_board, mover) {
+        Some(EndState::Win(winner)) if winner == mover => WIN - ply,
+        Some(EndState::Win(_)) => -(WIN - ply),
+        Some(EndState::Draw) => 0,
+        None => -minimax(rules, &new_board, mover.opposite(), depth, ply + 1),
+    }
+}
+
+// The root turns in the order they are generated with their scores in points, as the bot gives them
+fn minimax_scores(rules: &RuleSet, board: &Board, colour: Colour, depth: u64) -> Vec<(Turn, f64)> {
+    rules
+        .possible_turns(board, colour)
+        .into_iter()
+        .map(|turn| {
+            let score = score_turn(rules, &turn, board, depth, 0) as f64 / 100.0;
+            (turn, score)
+        })
+        .collect()
+}
+
+fn bot(colour: Colour, depth: u64, ordering: bool) -> Bot {
+    Bot::new(colour, depth).with_threads(1).with_weights(Weights::default()).with_ordering(ordering)
+}
+
+#[test]
+fn threads_score_the_root_turns_as_one_thread_does() {
+    let rules = RuleSet::default();
+    for (position, colour) in POSITIONS {
+        let board = notation::parse_board(position, rules.board).expect("valid board");
+        for depth in 1..=3 {
+            let expected = minimax_scores(&rules, &board, colour, depth);
+            // Root turns are taken by the threads in any order, the scores come back in the order of the turns
+            let scores = bot(colour, depth, true).with_threads(4).score_turns(&board, colour);
+            assert_eq!(scores, expected, "{} at depth {} on 4 threads", position, depth);
+        }
+    }
+}
+
+#[test]
+fn root_scores_match_minimax() {
+    let rules = RuleSet::default();
+    for (position, colour) in POSITIONS {
+        let board = notation::parse_board(position, rules.board).expect("valid board");
+        // Deeper searches could meet a position again and use the deeper score the table has for it
+        for depth in 1..=3 {
+            let expected = minimax_scores(&rules, &board, colour, depth);
+            for ordering in [true, false] {
+                let scores = bot(colour, depth, ordering).score_turns(&board, colour);
+                assert_eq!(scores, expected, "{} at depth {} with ordering {}", position, depth, ordering);
+            }
+        }
+    }
+}
+
+#[test]
+fn ordering_searches_fewer_nodes() {
+    let rules = RuleSet::default();
+    let nodes = |ordering: bool| -> u64 {
+        POSITIONS
+            .iter()
+            .map(|(position, colour)| {
+                let board = notation::parse_board(position, rules.board).expect("valid board");
+                let bot = bot(*colour, 4, ordering);
+                bot.score_turns(&board, *colour);
+                let stats = bot.stats();
+                stats.nodes + stats.quiescence_nodes
+            })
+            .sum()
+    };
+    let (ordered, unordered) = (nodes(true), nodes(false));
+    assert!(ordered < unordered, "{} nodes ordered, {} unordered", ordered, unordered);
+}
+
+#[test]
+fn threats_beyond_the_horizon_are_searched() {
+    let rules = RuleSet::default();
+    // Black fills the first stack with its own pieces unless White places there
+    let board = notation::parse_board("bb/w/-/w/-/-/-/-", rules.board).expect("valid board");
+    let bot = bot(Colour::White, 1, true);
+    let scores = bot.score_turns(&board, Colour::White);
+    for (turn, score) in &scores {
+        if *turn == Turn::Place(Colour::White, 0) {
+            assert!(*score > -(WIN_BOUND as f64) / 100.0, "{:?} blocks but scores {}", turn, score);
+        } else {
+            assert!(*score < -(WIN_BOUND as f64) / 100.0, "{:?} loses but scores {}", turn, score);
+        }
+    }
+    assert!(bot.stats().quiescence_nodes > 0);
+}
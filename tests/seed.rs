@@ -0,0 +1,57 @@
+use second_best::{
+    bot::{self, Bot, Stream},
+    eval::Weights,
+    game::*,
+    learn::{self, Training},
+};
+
+#[test]
+fn bots_break_ties_the_same_way_with_the_same_seed() {
+    let board = Board::new();
+    let choices = |seed: u64| {
+        let bot = Bot::new(Colour::White, 1).with_seed(seed);
+        (0..20).map(|_| bot.choose_turn(&board, None)).collect::<Vec<Turn>>()
+    };
+    assert_eq!(choices(7), choices(7));
+    // All first turns are equally good, so other seeds choose others
+    assert!((0..10).any(|seed| choices(seed) != choices(7)));
+}
+
+#[test]
+fn self_play_is_reproducible() {
+    let training = Training {
+        depth: 1,
+        exploration: 0.3,
+        ..Training::default()
+    };
+    let (positions, result) = learn::self_play(&training, &Weights::STANDARD, 42).unwrap();
+    assert_eq!(learn::self_play(&training, &Weights::STANDARD, 42).unwrap(), (positions.clone(), result));
+    assert_ne!(learn::self_play(&training, &Weights::STANDARD, 43).unwrap().0, positions);
+}
+
+#[test]
+fn bot_seeds_differ_by_colour_and_round() {
+    let mut game = Game::new().with_seed(1);
+    let white = game.bot_seed(Colour::White);
+    assert_ne!(white, game.bot_seed(Colour::Black));
+    game.propose_turn(Turn::Place(Colour::White, 0)).unwrap();
+    game.decide_second_best(Colour::Black, false).unwrap();
+    assert_ne!(game.bot_seed(Colour::White), white);
+    assert_eq!(Game::new().with_seed(1).bot_seed(Colour::White), white);
+}
+
+#[test]
+fn derived_seeds_do_not_overlap() {
+    let game = Game::new().with_seed(1);
+    let mut seeds = vec![
+        game.seed,
+        bot::sub_seed(game.seed, Stream::Exploration, 0),
+        bot::sub_seed(game.seed, Stream::TrainingGame, 1),
+        Game::new().with_seed(2).bot_seed(Colour::White),
+    ];
+    seeds.extend(Colour::ALL.map(|colour| game.bot_seed(colour)));
+    let count = seeds.len();
+    seeds.sort();
+    seeds.dedup();
+    assert_eq!(seeds.len(), count);
+}
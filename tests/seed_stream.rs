@@ -0,0 +1,18 @@
+use second_best::{
+    bot::{self, Bot},
+    game::*,
+};
+
+// The only test of this file, the seeds of the whole process are set from it
+#[test]
+fn helper_bots_leave_the_seeds_of_games_alone() {
+    let seeds = |helpers: bool| {
+        bot::set_seed(5);
+        let first = Game::new().seed;
+        if helpers {
+            Bot::new(Colour::White, 1).choose_turn(&Board::new(), None);
+        }
+        (first, Game::new().seed)
+    };
+    assert_eq!(seeds(true), seeds(false));
+}
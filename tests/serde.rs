@@ -0,0 +1,97 @@
+#![cfg(feature = "serde")]
+
+use second_best::{game::*, notation};
+use serde_json::json;
+
+#[test]
+fn turns_and_boards_have_a_stable_schema() {
+    let place = Turn::Place(Colour::White, 2);
+    let moved = Turn::Move(Colour::Black, 2, 6);
+    assert_eq!(serde_json::to_value(&place).unwrap(), json!({"type": "place", "colour": "white", "to": 2}));
+    assert_eq!(serde_json::to_value(&moved).unwrap(), json!({"type": "move", "colour": "black", "from": 2, "to": 6}));
+    assert_eq!(serde_json::from_value::<Turn>(json!({"type": "move", "colour": "black", "from": 2, "to": 6})).unwrap(), moved);
+
+    let board = notation::parse_board("wb/-/-/-/-/-/-/b", BoardConfig::STANDARD).unwrap();
+    let value = serde_json::to_value(&board).unwrap();
+    assert_eq!(value["places"][0], json!(["white", "black", null]));
+    assert_eq!(value["places"][1], json!([null, null, null]));
+    assert_eq!(value["config"], json!({"stacks": 8, "height": 3, "line": 4, "pieces": 8, "players": 2}));
+    assert_eq!(serde_json::from_value::<Board>(value).unwrap(), board);
+
+    assert_eq!(serde_json::to_value(EndState::Win(Colour::Red)).unwrap(), json!({"win": "red"}));
+    assert_eq!(serde_json::to_value(EndState::Draw).unwrap(), json!("draw"));
+}
+
+#[test]
+fn invalid_boards_are_rejected() {
+    let mut value = serde_json::to_value(Board::new()).unwrap();
+    value["places"][0] = json!([null, "white", null]);
+    assert!(serde_json::from_value::<Board>(value.clone()).is_err());
+    value["places"][0] = json!(["white"]);
+    assert!(serde_json::from_value::<Board>(value).is_err());
+}
+
+#[test]
+fn games_are_replayed() {
+    let mut game = Game::new();
+    game.propose_turn(Turn::Place(Colour::White, 0)).unwrap();
+    game.decide_second_best(Colour::Black, true).unwrap();
+    game.retry_turn(Turn::Place(Colour::White, 1)).unwrap();
+    game.propose_turn(Turn::Place(Colour::Black, 4)).unwrap();
+
+    let value = serde_json::to_value(&game).unwrap();
+    assert_eq!(value["callers"], json!(["black"]));
+    assert_eq!(value["phase"], json!({"second_best": {"type": "place", "colour": "black", "to": 4}}));
+    assert_eq!(value["seed"], json!(game.seed));
+    let read: Game = serde_json::from_value(value).unwrap();
+    assert_eq!(read.seed, game.seed);
+    assert_eq!(read.rounds, game.rounds);
+    assert_eq!(read.phase, game.phase);
+    assert_eq!(read.board, game.board);
+
+    game.decide_second_best(Colour::White, true).unwrap();
+    let read: Game = serde_json::from_value(serde_json::to_value(&game).unwrap()).unwrap();
+    assert_eq!(read.phase, Phase::Retry(Turn::Place(Colour::Black, 4)));
+
+    game.resign(Colour::Black).unwrap();
+    let read: Game = serde_json::from_value(serde_json::to_value(&game).unwrap()).unwrap();
+    assert_eq!(read.phase, game.phase);
+}
+
+#[test]
+fn illegal_games_are_rejected() {
+    let record = json!({
+        "rules": serde_json::to_value(second_best::rules::RuleSet::default()).unwrap(),
+        "rounds": [
+            {"proposed": {"type": "place", "colour": "white", "to": 0}, "second_turn": null},
+            {"proposed": {"type": "place", "colour": "white", "to": 1}, "second_turn": null},
+        ],
+    });
+    assert!(serde_json::from_value::<Game>(record).is_err());
+}
+
+#[test]
+fn early_endings_follow_from_the_game() {
+    let mut game = Game::new();
+    game.propose_turn(Turn::Place(Colour::White, 0)).unwrap();
+    game.decide_second_best(Colour::Black, false).unwrap();
+    let mut value = serde_json::to_value(&game).unwrap();
+    let read = |value: &serde_json::Value| serde_json::from_value::<Game>(value.clone());
+
+    for reason in ["resignation", "timeout", "forfeit"] {
+        value["phase"] = json!({"over": {"result": {"win": "white"}, "reason": reason, "stacks": []}});
+        let read = read(&value).unwrap();
+        assert_eq!(read.phase, Phase::Over(Outcome::new(EndState::Win(Colour::White), serde_json::from_value(json!(reason)).unwrap())));
+    }
+    // The board did not end the game, and nobody else wins or draws a game of two
+    for outcome in [
+        json!({"result": {"win": "white"}, "reason": "stack", "stacks": [0]}),
+        json!({"result": {"win": "black"}, "reason": "ring_line", "stacks": []}),
+        json!({"result": "draw", "reason": "resignation", "stacks": []}),
+        json!({"result": {"win": "red"}, "reason": "resignation", "stacks": []}),
+        json!({"result": {"win": "white"}, "reason": "resignation", "stacks": [0]}),
+    ] {
+        value["phase"] = json!({ "over": outcome });
+        assert!(read(&value).is_err(), "{}", outcome);
+    }
+}
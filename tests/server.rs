@@ -0,0 +1,123 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use second_best::{rules::RuleSet, server};
+
+const EMPTY_BOARD: &str = "-/-/-/-/-/-/-/-";
+
+fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || server::serve(listener, RuleSet::default()).unwrap());
+    address
+}
+
+struct Client {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Client {
+    fn connect(address: SocketAddr) -> Self {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let mut client = Self {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            stream,
+        };
+        assert_eq!(client.receive(), format!("welcome {}", server::SERVER_VERSION));
+        client
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stream, "{}", line).unwrap();
+    }
+
+    fn receive(&mut self) -> String {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        line.trim_end().to_owned()
+    }
+}
+
+#[test]
+fn players_and_spectators_share_a_table() {
+    let address = start_server();
+    let mut white = Client::connect(address);
+    white.send("create w");
+    assert_eq!(white.receive(), "joined 0 w");
+    assert_eq!(white.receive(), format!("state 0 {} w propose", EMPTY_BOARD));
+
+    let mut black = Client::connect(address);
+    black.send("list");
+    assert_eq!(black.receive(), "table 0 taken open waiting");
+    assert_eq!(black.receive(), "end");
+    black.send("join 0 b");
+    assert_eq!(black.receive(), "joined 0 b");
+    assert_eq!(black.receive(), format!("state 0 {} w propose", EMPTY_BOARD));
+    assert_eq!(white.receive(), format!("state 0 {} w propose", EMPTY_BOARD));
+
+    let mut spectator = Client::connect(address);
+    spectator.send("join 0 w");
+    assert_eq!(spectator.receive(), "error seat w is taken");
+    spectator.send("join 0 spectator");
+    assert_eq!(spectator.receive(), "joined 0 spectator");
+    for client in [&mut white, &mut black, &mut spectator] {
+        assert_eq!(client.receive(), format!("state 0 {} w propose", EMPTY_BOARD));
+    }
+    spectator.send("resign");
+    assert_eq!(spectator.receive(), "error spectators cannot play");
+
+    white.send("propose 1");
+    for client in [&mut white, &mut black, &mut spectator] {
+        assert_eq!(client.receive(), format!("state 0 {} w secondbest 1", EMPTY_BOARD));
+    }
+    black.send("secondbest no");
+    for client in [&mut white, &mut black, &mut spectator] {
+        assert_eq!(client.receive(), "round 0 1");
+        assert_eq!(client.receive(), "state 0 w/-/-/-/-/-/-/- b propose");
+    }
+    white.send("propose 2");
+    assert_eq!(white.receive(), "error it is not your turn");
+
+    black.send("resign");
+    for client in [&mut white, &mut black, &mut spectator] {
+        assert_eq!(client.receive(), "state 0 w/-/-/-/-/-/-/- b over w resignation");
+    }
+}
+
+#[test]
+fn bots_take_their_seat() {
+    let address = start_server();
+    let mut black = Client::connect(address);
+    black.send("create b bot 1");
+    assert_eq!(black.receive(), "joined 0 b");
+    assert_eq!(black.receive(), format!("state 0 {} w propose", EMPTY_BOARD));
+    let proposed = black.receive();
+    assert!(proposed.starts_with(&format!("state 0 {} w secondbest ", EMPTY_BOARD)), "{}", proposed);
+    black.send("secondbest no");
+    assert!(black.receive().starts_with("round 0 "));
+    assert!(black.receive().ends_with(" b propose"));
+}
+
+#[test]
+fn deep_bots_are_refused() {
+    let address = start_server();
+    let mut client = Client::connect(address);
+    client.send(&format!("create bot {}", server::MAX_BOT_DEPTH + 1));
+    assert_eq!(client.receive(), format!("error bots search to a depth of at most {}", server::MAX_BOT_DEPTH));
+    client.send("list");
+    assert_eq!(client.receive(), "end");
+}
+
+#[test]
+fn three_players_are_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut rules = RuleSet::default();
+    rules.set("variant", "three").unwrap();
+    assert!(server::serve(listener, rules).is_err());
+}
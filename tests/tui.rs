@@ -0,0 +1,39 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+// Run the full-screen game in a pseudo terminal made by `script`, pressing the keys one after the other
+fn run_tui(keys: &[&str]) -> String {
+    let command = format!("{} --seed 1 --tui", env!("CARGO_BIN_EXE_second_best"));
+    let mut child = Command::new("script")
+        .args(["-qfec", &command, "/dev/null"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("script from util-linux runs the game in a terminal");
+    let mut stdin = child.stdin.take().unwrap();
+    for key in keys {
+        // Give the game time to switch the terminal to raw mode and to draw
+        thread::sleep(Duration::from_millis(300));
+        stdin.write_all(key.as_bytes()).unwrap();
+        stdin.flush().unwrap();
+    }
+    let mut screen = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut screen).unwrap();
+    child.wait().unwrap();
+    screen
+}
+
+#[test]
+fn stacks_are_chosen_by_number_or_with_the_cursor() {
+    const RIGHT: &str = "\x1b[C";
+    let screen = run_tui(&["p", "3", "n", RIGHT, RIGHT, "\r", "s", "q"]);
+    assert!(screen.contains("White, place a piece."));
+    assert!(screen.contains("White proposes to place a piece at 3. Black, second best?"));
+    // The cursor stays on the stack chosen last
+    assert!(screen.contains("Black proposes to place a piece at 5. White, second best?"));
+    assert!(screen.contains("Second best! Try a new move."));
+}
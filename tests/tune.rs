@@ -0,0 +1,73 @@
+use second_best::{
+    bot::Bot,
+    eval::{self, Weights},
+    game::*,
+    notation,
+    rules::RuleSet,
+    tune,
+};
+
+// Let bots play a game to its end, the seed makes them choose differently between equal turns
+fn play(seed: u64) -> Option<Game> {
+    let mut game = Game::new().with_seed(seed);
+    let bots = [Colour::White, Colour::Black].map(|colour| Bot::new(colour, 1).with_seed(game.bot_seed(colour)));
+    while game.rounds.len() < 100 {
+        let result = match (game.phase.clone(), game.waiting_for()) {
+            (Phase::Propose, Some(colour)) => {
+                let bot = &bots[if colour == Colour::White { 0 } else { 1 }];
+                game.propose_turn(bot.choose_turn(&game.board, None))
+            }
+            (Phase::SecondBest(_), Some(colour)) => game.decide_second_best(colour, false),
+            _ => break,
+        };
+        result.unwrap();
+    }
+    matches!(game.phase, Phase::Over(_)).then_some(game)
+}
+
+fn is_win(rules: &RuleSet, board: &Board, turn: &Turn) -> bool {
+    let mut new_board = board.clone();
+    new_board.do_turn(turn);
+    rules.is_won(&new_board, turn.colour()) == Some(EndState::Win(turn.colour()))
+}
+
+#[test]
+fn tuning_lowers_the_error_on_quiet_positions_of_finished_games() {
+    let rules = RuleSet::default();
+    let games: Vec<Game> = (0..20).filter_map(play).take(6).collect();
+    assert_eq!(games.len(), 6);
+    let records: Vec<String> = games.iter().map(|game| notation::record_to_string(&game.rounds)).collect();
+    let samples = tune::read_collection(&records.join("\n"), &rules).unwrap();
+
+    // The positions used are those where neither colour can win with its next turn
+    let mut quiet = Vec::new();
+    let mut positions = 0;
+    for game in &games {
+        let mut board = Board::new();
+        for played in &game.rounds {
+            board.do_turn(played.played());
+            if rules.is_won(&board, played.played().colour()).is_some() {
+                continue;
+            }
+            positions += 1;
+            let can_win = Colour::playing(2)
+                .iter()
+                .any(|colour| rules.possible_turns(&board, *colour).iter().any(|turn| is_win(&rules, &board, turn)));
+            if !can_win {
+                quiet.push(eval::features(&board, Colour::White));
+            }
+        }
+    }
+    assert!(quiet.len() < positions);
+    assert_eq!(samples.iter().map(|sample| sample.features).collect::<Vec<_>>(), quiet);
+
+    // Unfinished games are left out unless they come with a result
+    let unfinished = notation::record_to_string(&games[0].rounds[..games[0].rounds.len() - 1]);
+    let with_unfinished = format!("# games\n{}\n\n{}\n", records.join("\n"), unfinished);
+    assert_eq!(tune::read_collection(&with_unfinished, &rules).unwrap().len(), samples.len());
+    assert!(!tune::read_collection(&format!("{} draw", unfinished), &rules).unwrap().is_empty());
+
+    let start = Weights([0.0; eval::FEATURES]);
+    let tuned = tune::tune(start, &samples, |_, _, _| {});
+    assert!(tune::error(&tuned, &samples) < tune::error(&start, &samples));
+}